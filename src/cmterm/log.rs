@@ -13,6 +13,7 @@ pub(super) struct _TerminalLogData {
     pub(super) title: String,
     pub(super) lines: RingBuffer<String, 256>,
     pub(super) disk_log_path: Option<String>,
+    pub(super) headless: bool,
 }
 
 pub struct Log {
@@ -39,13 +40,31 @@ impl Log {
                 _TerminalLogData { 
                     title: title.clone(),
                     lines: RingBuffer::new(),
-                    disk_log_path: None
+                    disk_log_path: None,
+                    headless: false
                 }
             ), //.with_name(format!("{}.log_data", title)),
             input: input
         }
     }
 
+    /// Creates a log which writes straight to stderr rather than to a terminal interface
+    /// Requests for input from a headless log always fail
+    pub fn new_headless(name: impl Into<String>) -> Self {
+        let (render_s, _) = std::sync::mpsc::channel();
+        let (_, input_r) = std::sync::mpsc::channel();
+        let log = Log::new(name, Arc::new(Input::new(render_s, input_r)));
+        log.data.lock().unwrap().headless = true;
+        return log;
+    }
+
+    fn headless_err(&self) -> io::Result<()> {
+        return match self.data.lock().unwrap().headless {
+            true => Err(io::Error::new(io::ErrorKind::Unsupported, "cannot request input without an interactive terminal")),
+            false => Ok(())
+        };
+    }
+
     #[allow(unused)]
     pub fn with_disk_log(mut self, path: impl Into<String>) -> Self {
         self.data.lock().unwrap().disk_log_path = Some(path.into());
@@ -119,6 +138,7 @@ impl Log {
         for line in msg_lines {
             let line_styled = styler(&line);
             let final_line = format!("{} {}", current_prefix, line_styled).replace('\t', "  ");
+            if data.headless { eprintln!("{}", final_line); }
             data.lines.push(final_line);
             current_prefix = &prefix_empty;
         }
@@ -147,15 +167,18 @@ impl Log {
     }
 
     pub fn wait_for_enter(&self, prompt: impl Into<String>) -> io::Result<()> {
+        self.headless_err()?;
         self.input.wait_for_enter(self.name(), prompt)?;
         return Ok(());
     }
 
     pub fn request_string(&self, prompt: impl Into<String>) -> io::Result<String> {
+        self.headless_err()?;
         return self.input.request_string(self.name(), prompt);
     }
 
     pub fn request_password(&self, prompt: impl Into<String>) -> io::Result<String> {
+        self.headless_err()?;
         return self.input.request_password(self.name(), prompt)
     }
 
@@ -0,0 +1,282 @@
+use std::{fs, io::{self, Read}, path::{Path, PathBuf}};
+
+use git2::{Config, ObjectType, Oid, Repository};
+
+use crate::cmterm;
+use crate::features;
+use crate::mission_codes::{self, CodelessInfo, MissionCode};
+use crate::program_info::{ProgramArgs, ProgramCommand};
+use crate::repo_management;
+
+/// Reads a mission code from the given file, or stdin if no file or "-" is given
+/// A single trailing line ending is removed, as editors tend to append one when saving
+fn read_code(code_path: Option<&Path>) -> io::Result<String> {
+    let mut code = String::new();
+    match code_path {
+        Some(p) if p.as_os_str() != "-" => code = fs::read_to_string(p)?,
+        _ => { io::stdin().read_to_string(&mut code)?; }
+    };
+
+    let trimmed_len = code.strip_suffix('\n').map_or(code.len(), |c| { c.strip_suffix('\r').unwrap_or(c).len() });
+    code.truncate(trimmed_len);
+    return Ok(code);
+}
+
+fn read_mission_code(code_path: Option<&Path>, log: &cmterm::Log) -> Option<MissionCode> {
+    let code = match read_code(code_path) {
+        Ok(c) => c,
+        Err(e) => {
+            log.log_err(format!("Failed to read mission code with error:\n{}", e));
+            return None;
+        }
+    };
+
+    return match MissionCode::parse_from(&code) {
+        Ok(c) => Some(c),
+        Err(e) => {
+            log.log_err(format!("Input was not a valid custom mission code, with reason:\n{}", e));
+            None
+        }
+    };
+}
+
+fn command_repo_path(args: &ProgramArgs) -> Result<PathBuf, String> {
+    return match args.repo_path.as_ref() {
+        Some(p) => Ok(p.clone()),
+        None => match std::env::current_dir() {
+            Ok(p) => Ok(p),
+            Err(e) => Err(format!("no repo path provided and failed to retrieve working directory with error {e}"))
+        }
+    };
+}
+
+fn command_repo(args: &ProgramArgs, log: &cmterm::Log) -> Option<Repository> {
+    let repo_path = match command_repo_path(args) {
+        Ok(p) => p,
+        Err(e) => {
+            log.log_err(e);
+            return None;
+        }
+    };
+
+    return match repo_management::get_repo(&repo_path) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            log.log_err(format!("Failed to open repo @ \"{}\" with error:\n{}", repo_path.display(), e));
+            None
+        }
+    };
+}
+
+fn init(args: &ProgramArgs, log: &cmterm::Log) -> i32 {
+    let repo = match command_repo(args, log) {
+        Some(r) => r,
+        None => return 1
+    };
+
+    let mut created_count = 0;
+    for mut feature in features::construct_all_registered() {
+        match repo_management::init(&repo, feature.as_mut()) {
+            Ok(created) => {
+                if created.is_empty() {
+                    log.log(format!("Feature {} is already initialized", feature));
+                }
+                for c in created {
+                    log.log_success(format!("Initialized {} for feature {}", c, feature));
+                    created_count += 1;
+                }
+            },
+            Err(e) => {
+                log.log_err(format!("Failed to initialize feature {} with error:\n{}", feature, e));
+                return 1;
+            }
+        }
+    }
+
+    if created_count > 0 {
+        log.log("Commit and push the created files to finish preparing the repo");
+    }
+
+    return 0;
+}
+
+fn inspect(code_path: Option<&Path>, log: &cmterm::Log) -> i32 {
+    let mission_code = match read_mission_code(code_path, log) {
+        Some(c) => c,
+        None => return 1
+    };
+
+    println!("Codeless Version: {}", mission_code.codeless_fmt_version.version());
+    println!("Features ({}): [{}]", mission_code.codeless_features.len(), mission_code.feature_display());
+    for unknown in mission_code.unknown_features() {
+        println!("  Warning: {}", unknown);
+    }
+    println!("Gist URL: {}", mission_code.gist_url.as_deref().unwrap_or("None"));
+    println!("Gist Remote: {}", mission_code.gist_remote.as_deref().unwrap_or("None"));
+    println!("Payload Encoding: {}", mission_code.payload_encoding());
+
+    let files = mission_code.files();
+    println!("Files ({}):", files.len());
+    for ((name, data), (_, decoded)) in files.into_iter().zip(mission_code.payloads()) {
+        let blob_hash = match Oid::hash_object(ObjectType::Blob, decoded.as_bytes()) {
+            Ok(oid) => oid.to_string(),
+            Err(e) => format!("unavailable ({})", e.message())
+        };
+
+        println!("  {}", name);
+        println!("    Encoded Size: {} bytes", data.len());
+        println!("    Decoded Size: {} bytes", decoded.len());
+        println!("    Blob Hash: {}", blob_hash);
+        if decoded.len() > mission_codes::MAX_CODE_SIZE {
+            println!("    Published As: {} parts, listed in {}.index", decoded.len().div_ceil(mission_codes::MAX_CODE_SIZE), name);
+        }
+    }
+
+    return 0;
+}
+
+fn publish(code_path: Option<&Path>, copy: bool, args: &ProgramArgs, log: &cmterm::Log) -> i32 {
+    register_external_features(args, log);
+
+    let mut mission_code = match read_mission_code(code_path, log) {
+        Some(c) => c,
+        None => return 1
+    };
+
+    match mission_code.report_unknown_features(args.strict_features, log) {
+        Ok(_) => (),
+        Err(_) => return 1
+    };
+
+    let repo = match command_repo(args, log) {
+        Some(r) => r,
+        None => return 2
+    };
+
+    log.log(format!("Publishing mission code with features [{}]", mission_code.feature_display()));
+    let (author, author_email) = mission_code.commit_author();
+    let outcome = match repo_management::publish(&repo, &mut mission_code, author, author_email, args.in_memory, copy) {
+        Ok(o) => o,
+        Err(e) => {
+            log.log_err(format!("Failed to publish mission code with error:\n{}", e));
+            return 2;
+        }
+    };
+
+    match outcome.unchanged {
+        true => log.log_success(format!("Mission data unchanged since commit {}, reused existing link", outcome.commit)),
+        false => log.log_success(format!("Published mission version {}", mission_code.version_display().unwrap_or(String::from("Untracked"))))
+    };
+
+    for file in outcome.files {
+        println!("{}", file.url);
+    }
+
+    return 0;
+}
+
+fn migrate(code_paths: &[PathBuf], dry_run: bool, args: &ProgramArgs, log: &cmterm::Log) -> i32 {
+    register_external_features(args, log);
+
+    let repo = match command_repo(args, log) {
+        Some(r) => r,
+        None => return 1
+    };
+    let mut files = repo_management::RepoFiles::new(&repo);
+
+    let mut changes = Vec::<String>::new();
+    let mut mission_codes = Vec::<MissionCode>::with_capacity(code_paths.len());
+    for code_path in code_paths {
+        // Codes are rewritten in place, which isn't possible for stdin
+        if code_path.as_os_str() == "-" {
+            log.log_err("Mission codes can't be migrated from stdin, pass a file instead");
+            return 1;
+        }
+
+        let mut mission_code = match read_mission_code(Some(code_path), log) {
+            Some(c) => c,
+            None => return 1
+        };
+
+        match mission_code.upgrade() {
+            Some(version) => {
+                if !dry_run {
+                    match fs::write(code_path, mission_code.to_code_string()) {
+                        Ok(_) => (),
+                        Err(e) => {
+                            log.log_err(format!("Failed to write upgraded mission code to {} with error:\n{}", code_path.display(), e));
+                            return 1;
+                        }
+                    };
+                }
+                changes.push(format!("{}: upgraded codeless version {} to {}", code_path.display(), version, CodelessInfo::NEWEST.version()));
+            },
+            None => ()
+        };
+
+        for feature in &mission_code.codeless_features {
+            match feature.repo_migrate(&mut files, dry_run) {
+                Ok(mut c) => changes.append(&mut c),
+                Err(e) => {
+                    log.log_err(format!("Failed to migrate feature {} with error:\n{}", feature, e));
+                    return 1;
+                }
+            };
+        }
+        mission_codes.push(mission_code);
+    }
+
+    // Legacy state is only removed once every code has had the chance to migrate from it
+    for feature in mission_codes.iter().flat_map(|c| { c.codeless_features.iter() }) {
+        match feature.repo_retire(&mut files, dry_run) {
+            // Dry runs leave the legacy state in place, so every code would otherwise report retiring it
+            Ok(c) => changes.extend(c.into_iter().filter(|change| { !changes.contains(change) }).collect::<Vec<String>>()),
+            Err(e) => {
+                log.log_err(format!("Failed to retire legacy state of feature {} with error:\n{}", feature, e));
+                return 1;
+            }
+        };
+    }
+
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    match (changes.is_empty(), dry_run) {
+        (true, _) => log.log_success("Everything is already up to date"),
+        (false, true) => log.log(format!("Dry run, {} changes were not written", changes.len())),
+        (false, false) => log.log_success(format!("Made {} changes, commit and push any migrated repo files to finish", changes.len()))
+    };
+
+    return 0;
+}
+
+/// Git config of the command's repo, falling back to the global config when run outside of a repo
+fn command_config(args: &ProgramArgs) -> Option<Config> {
+    let repo_config = command_repo_path(args).ok()
+        .and_then(|p| { Repository::open(p).ok() })
+        .and_then(|r| { r.config().ok() });
+
+    return match repo_config {
+        Some(c) => Some(c),
+        None => Config::open_default().ok()
+    };
+}
+
+/// Registers the external features declared in the command's git config, for commands which act upon them
+fn register_external_features(args: &ProgramArgs, log: &cmterm::Log) {
+    match command_config(args) {
+        Some(config) => features::external::register_from_config(&config, log),
+        None => log.log_warn("Failed to open git config, external features will be unavailable")
+    };
+}
+
+/// Runs the given command to completion, returning the program's exit code
+pub fn run(command: &ProgramCommand, args: &ProgramArgs, log: &cmterm::Log) -> i32 {
+    return match command {
+        ProgramCommand::Init => init(args, log),
+        ProgramCommand::Inspect { code_path } => inspect(code_path.as_deref(), log),
+        ProgramCommand::Migrate { dry_run, code_paths } => migrate(code_paths, *dry_run, args, log),
+        ProgramCommand::Publish { copy, code_path } => publish(code_path.as_deref(), *copy, args, log)
+    };
+}
@@ -0,0 +1,90 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Author";
+
+/// Identity a publish commit is authored by, passed as `Author=<name> <<email>>` or `Author=<name>`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: Option<String>
+}
+
+impl std::str::FromStr for CommitAuthor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, email) = match s.trim().strip_suffix('>').and_then(|s| { s.rsplit_once('<') }) {
+            Some((name, email)) => (name.trim(), Some(email.trim())),
+            None => (s.trim(), None)
+        };
+
+        if name.is_empty() {
+            return Err(String::from("author name was empty"));
+        }
+        if name.contains(['<', '>']) {
+            return Err(format!("expected author of form \"name <email>\", got \"{}\"", s));
+        }
+
+        let email = match email {
+            Some(e) if e.is_empty() || e.contains(['<', '>']) => return Err(format!("author email \"{}\" was not valid", e)),
+            Some(e) => Some(e.to_string()),
+            None => None
+        };
+
+        return Ok(CommitAuthor { name: name.to_string(), email: email });
+    }
+}
+
+impl Display for CommitAuthor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match &self.email {
+            Some(email) => f.write_fmt(format_args!("{} <{}>", self.name, email)),
+            None => f.write_str(&self.name)
+        };
+    }
+}
+
+/// Author of the publish commit, in place of the uploader
+pub struct Author {
+    author: CommitAuthor
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let arg = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Author { author: features::parse_feature_arg::<CommitAuthor>(NAME, arg)? }));
+}
+
+impl CodelessFeature for Author {
+    fn name(&self) -> &str { return NAME; }
+    fn commit_author(&self) -> Option<&CommitAuthor> { return Some(&self.author); }
+}
+
+impl Display for Author {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[{}]", NAME, self.author.name));
+    }
+}
+
+impl RepoItem for Author {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Author {
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for Author {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
@@ -0,0 +1,97 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature, MissionContext};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Changelog";
+
+const MISSION_CHANGELOG_FILE: &'static str = "CHANGELOG.md";
+
+/// Release notes prepended to the repo changelog on every publish, tagged with the mission version once processed
+pub struct Changelog {
+    notes: String,
+    version_tag: Option<String>
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let notes = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Changelog { notes: notes.to_string(), version_tag: None }));
+}
+
+impl CodelessFeature for Changelog {
+    fn name(&self) -> &str { return NAME; }
+    fn publishes_content(&self) -> bool { return true; }
+
+    fn attach(&mut self, mission: &MissionContext) {
+        self.version_tag = mission.version_tag.map(|t| { t.to_string() });
+    }
+}
+
+impl Display for Changelog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(NAME);
+    }
+}
+
+impl RepoItem for Changelog {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Changelog {
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        let existing = match files.file_exists(MISSION_CHANGELOG_FILE)? {
+            true => files.read_file(MISSION_CHANGELOG_FILE)?,
+            false => String::new()
+        };
+
+        let date = chrono::Local::now().format("%Y-%m-%d");
+        let heading = match &self.version_tag {
+            Some(tag) => format!("## {} - {}", tag, date),
+            None => format!("## {}", date)
+        };
+
+        let changelog = format!("{}\n\n{}\n\n{}", heading, self.notes.trim(), existing);
+        files.overwrite_file(MISSION_CHANGELOG_FILE, &format!("{}\n", changelog.trim_end()))?;
+        return Ok(());
+    }
+
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> {
+        return features::repo_file_valid(files, MISSION_CHANGELOG_FILE);
+    }
+}
+
+impl RepoDerivable for Changelog {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository;
+
+    #[test]
+    fn entry_prepended() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+        files.overwrite_file(MISSION_CHANGELOG_FILE, "## 2 - 2024-01-01\n\nAdded the vault\n").unwrap();
+
+        let mut feature = construct(Some("  Fixed the vent  ")).unwrap();
+        feature.attach(&MissionContext { gist_file: "mission.txt", payload: "", version_tag: Some("3") });
+        feature.repo_publish(&mut files).unwrap();
+
+        let date = chrono::Local::now().format("%Y-%m-%d");
+        assert_eq!(
+            files.read_file(MISSION_CHANGELOG_FILE).unwrap(),
+            format!("## 3 - {}\n\nFixed the vent\n\n## 2 - 2024-01-01\n\nAdded the vault\n", date)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature};
+use crate::mission_codes::{MissionCodeParseError, MissionFile};
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Checksum";
+
+const CHECKSUM_DELIMIT: char = ':';
+
+/// Length & CRC32 of the undecoded data of every file, passed as `Checksum=<byte length>:<crc32 as 8 hex digits>`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PayloadChecksum {
+    pub length: usize,
+    pub crc32: u32
+}
+
+impl PayloadChecksum {
+    /// Checksums the data of the given files in order, as it appears within the mission code
+    pub fn of_files(files: &[MissionFile]) -> Self {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut length = 0;
+        for file in files {
+            hasher.update(file.data.as_bytes());
+            length += file.data.len();
+        }
+
+        return PayloadChecksum { length: length, crc32: hasher.finalize() };
+    }
+}
+
+impl std::str::FromStr for PayloadChecksum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (length, crc32) = match s.split_once(CHECKSUM_DELIMIT) {
+            Some(parts) => parts,
+            None => return Err(format!("expected checksum of form length{}crc32, got \"{}\"", CHECKSUM_DELIMIT, s))
+        };
+
+        let length = match length.parse::<usize>() {
+            Ok(l) => l,
+            Err(e) => return Err(format!("checksum length \"{}\" was not a valid usize with error {}", length, e))
+        };
+
+        if crc32.len() != 8 {
+            return Err(format!("checksum crc32 \"{}\" was not 8 hex digits", crc32));
+        }
+        let crc32 = match u32::from_str_radix(crc32, 16) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("checksum crc32 \"{}\" was not valid hex with error {}", crc32, e))
+        };
+
+        return Ok(PayloadChecksum { length: length, crc32: crc32 });
+    }
+}
+
+impl Display for PayloadChecksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}{}{:08x}", self.length, CHECKSUM_DELIMIT, self.crc32));
+    }
+}
+
+/// Integrity check of the mission data, verified when the mission code is parsed so truncated or mangled data is never published
+pub struct Checksum {
+    checksum: PayloadChecksum
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let arg = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Checksum { checksum: features::parse_feature_arg::<PayloadChecksum>(NAME, arg)? }));
+}
+
+impl CodelessFeature for Checksum {
+    fn name(&self) -> &str { return NAME; }
+
+    fn verify_files(&self, files: &[MissionFile]) -> Result<(), MissionCodeParseError> {
+        let actual = PayloadChecksum::of_files(files);
+        return match actual == self.checksum {
+            true => Ok(()),
+            false => Err(MissionCodeParseError::ChecksumMismatch { expected: self.checksum.to_string(), actual: actual.to_string() })
+        };
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[{:08x}]", NAME, self.checksum.crc32));
+    }
+}
+
+impl RepoItem for Checksum {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Checksum {
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for Checksum {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature};
+use crate::mission_codes::MissionCodeParseError;
+use crate::payload::PayloadEncoding;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Encoding";
+
+/// Encoding of the mission data, decoded before the data is written to the gist file
+pub struct Encoding {
+    encoding: PayloadEncoding
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let arg = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Encoding { encoding: features::parse_feature_arg::<PayloadEncoding>(NAME, arg)? }));
+}
+
+impl CodelessFeature for Encoding {
+    fn name(&self) -> &str { return NAME; }
+    fn payload_encoding(&self) -> Option<PayloadEncoding> { return Some(self.encoding); }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[{}]", NAME, self.encoding));
+    }
+}
+
+impl RepoItem for Encoding {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Encoding {
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for Encoding {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
@@ -0,0 +1,260 @@
+use std::{fmt::Display, io::{self, Write}, path::{Component, Path}, process::{Command, Stdio}, thread};
+
+use git2::Config;
+
+use crate::cmterm;
+use crate::features::{self, CodelessFeature, MissionContext};
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+/// Matches git config entries of the form `codeless.<Feature>.command`
+/// The feature name is a subsection so git preserves its case
+const CONFIG_ENTRY_PATTERN: &'static str = r"^codeless\..+\.command$";
+const CONFIG_ENTRY_PREFIX: &'static str = "codeless.";
+const CONFIG_ENTRY_SUFFIX: &'static str = ".command";
+
+const ENV_FEATURE_ARGUMENT: &'static str = "CODELESS_FEATURE_ARGUMENT";
+const ENV_MISSION_FILE: &'static str = "CODELESS_MISSION_FILE";
+const ENV_MISSION_VERSION: &'static str = "CODELESS_MISSION_VERSION";
+
+/// A feature backed by a local executable declared in git config, run while changes are written to the repo
+///
+/// The executable is run from the repo workdir with the workdir as its only argument and the decoded mission data on stdin
+/// The feature argument, gist file & mission version are passed through the CODELESS_* environment variables
+/// Every line written to stdout is taken as the repo relative path of a file the executable wrote
+pub struct ExternalCommand {
+    name: String,
+    command: String,
+    argument: Option<String>,
+    mission_file: String,
+    version_tag: Option<String>,
+    payload: String
+}
+
+/// Registers an external feature for every `codeless.<Feature>.command` entry in the given config
+/// Entries naming an already registered feature are ignored, so built-in features can't be replaced
+pub fn register_from_config(config: &Config, log: &cmterm::Log) {
+    let mut commands = Vec::<(String, String)>::new();
+    let mut entries = match config.entries(Some(CONFIG_ENTRY_PATTERN)) {
+        Ok(e) => e,
+        Err(e) => {
+            log.log_warn(format!("Failed to read external features from git config with error:\n{}", e));
+            return;
+        }
+    };
+
+    while let Some(entry) = entries.next() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                log.log_warn(format!("Skipping unreadable git config entry with error:\n{}", e));
+                continue;
+            }
+        };
+
+        let (name, command) = match (entry.name(), entry.value()) {
+            (Some(n), Some(v)) => (n, v),
+            _ => continue
+        };
+
+        let name = match name.strip_prefix(CONFIG_ENTRY_PREFIX).and_then(|n| { n.strip_suffix(CONFIG_ENTRY_SUFFIX) }) {
+            Some(n) => n,
+            None => continue
+        };
+
+        // Later entries come from more specific config levels, and override earlier ones
+        commands.retain(|(n, _)| { n != name });
+        commands.push((name.to_string(), command.to_string()));
+    }
+
+    let registered = features::registered_names();
+    for (name, command) in commands {
+        if registered.contains(&name) {
+            log.log_warn(format!("Ignoring external command for feature {} as it is already registered", name));
+            continue;
+        }
+
+        let feature_name = name.clone();
+        features::register(&name, Box::new(move |arg| {
+            return Ok(Box::new(ExternalCommand {
+                name: feature_name.clone(),
+                command: command.clone(),
+                argument: arg.map(|a| { a.to_string() }),
+                mission_file: String::new(),
+                version_tag: None,
+                payload: String::new()
+            }));
+        }));
+        log.log(format!("Registered external feature {}", name));
+    }
+}
+
+impl CodelessFeature for ExternalCommand {
+    fn name(&self) -> &str { return &self.name; }
+    fn publishes_content(&self) -> bool { return true; }
+
+    fn attach(&mut self, mission: &MissionContext) {
+        self.mission_file = mission.gist_file.to_string();
+        self.payload = mission.payload.to_string();
+        self.version_tag = mission.version_tag.map(|t| { t.to_string() });
+    }
+}
+
+impl Display for ExternalCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[External]", self.name));
+    }
+}
+
+impl RepoItem for ExternalCommand {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for ExternalCommand {
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        let thread_log = cmterm::Log::get();
+        let command_failed = |reason: String| { RepoError::CommandFailed { command: self.command.clone(), reason: reason } };
+
+        // In-memory publishes never touch the workdir, so there is nowhere for the command to write
+        let workdir = match files.workdir() {
+            Some(w) => w,
+            None => return Err(command_failed(String::from("external features need a working directory, so can't run when publishing in memory")))
+        };
+
+        // Relative paths are resolved against the workdir, as the command runs from within it
+        let program = match self.command.contains(['/', '\\']) && Path::new(&self.command).is_relative() {
+            true => workdir.join(&self.command),
+            false => self.command.clone().into()
+        };
+
+        let mut command = Command::new(program);
+        command.arg(workdir)
+            .current_dir(workdir)
+            .env(ENV_MISSION_FILE, &self.mission_file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        match &self.argument {
+            Some(arg) => { command.env(ENV_FEATURE_ARGUMENT, arg); },
+            None => ()
+        };
+        match &self.version_tag {
+            Some(tag) => { command.env(ENV_MISSION_VERSION, tag); },
+            None => ()
+        };
+
+        let mut child = match command.spawn() {
+            Ok(c) => c,
+            Err(e) => return Err(command_failed(format!("failed to start with error {}", e)))
+        };
+
+        // Written from another thread so a command filling its stdout before reading stdin can't deadlock
+        let mut stdin = child.stdin.take().unwrap();
+        let payload = self.payload.clone();
+        let stdin_writer = thread::spawn(move || { return stdin.write_all(payload.as_bytes()); });
+
+        let output = match child.wait_with_output() {
+            Ok(o) => o,
+            Err(e) => return Err(command_failed(format!("failed to wait for exit with error {}", e)))
+        };
+
+        match stdin_writer.join() {
+            Ok(Ok(_)) => (),
+            // Commands aren't required to read the payload
+            Ok(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe => (),
+            Ok(Err(e)) => return Err(command_failed(format!("failed to write payload with error {}", e))),
+            Err(_) => return Err(command_failed(String::from("payload writer thread panicked")))
+        };
+
+        for written in String::from_utf8_lossy(&output.stdout).lines().map(|l| { l.trim() }).filter(|l| { !l.is_empty() }) {
+            // Only paths within the repo can be committed, or rolled back if the publish fails
+            let within_repo = Path::new(written).components().all(|c| { matches!(c, Component::Normal(_) | Component::CurDir) });
+            match within_repo {
+                true => {
+                    files.record_external(written);
+                    thread_log.log(format!("Feature {} wrote {}", self.name, written));
+                },
+                false => thread_log.log_warn(format!("Feature {} reported writing {}, which is outside of the repo", self.name, written))
+            };
+        }
+
+        // Checked after recording the written files, so the writes of a failed command are still rolled back
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(command_failed(format!("exited with {}: {}", output.status, stderr.trim())));
+        }
+
+        return Ok(());
+    }
+
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for ExternalCommand {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt};
+    use git2::Repository;
+    use crate::mission_codes::MissionCode;
+    use crate::repo_management::{self, tests::*, PublishOutcome};
+
+    /// Registers the feature as a shell script that writes the payload to index.txt, also reporting a path outside of the repo
+    fn register_script(repo: &Repository, dir: &Path, feature: &str, exit_code: i32) {
+        let script_path = dir.join(format!("{}.sh", feature));
+        fs::write(&script_path, format!("#!/bin/sh\ncat > index.txt\necho outside > ../outside.txt\necho index.txt\necho ../outside.txt\nexit {}\n", exit_code)).unwrap();
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str(&format!("codeless.{}.command", feature), script_path.to_str().unwrap()).unwrap();
+        register_from_config(&config.snapshot().unwrap(), &cmterm::Log::get());
+    }
+
+    fn publish_with(repo: &Repository, feature: &str, data: &str) -> Result<PublishOutcome, RepoError> {
+        let code = format!("_infilengine_cm_codeless_|0|2|MissionVersion|{}|mission.txt|None|origin|{}", feature, data);
+        let mut mission_code = MissionCode::parse_from(&code).unwrap();
+        return repo_management::publish(repo, &mut mission_code, None, None, false, false);
+    }
+
+    #[test]
+    fn written_files_committed() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[("mission.txt", "old"), ("index.txt", "old index")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        register_script(&repo, dir.path(), "TestWriteIndex", 0);
+
+        let outcome = publish_with(&repo, "TestWriteIndex", "new").unwrap();
+        assert_eq!(commit_file(&repo, outcome.commit, "index.txt").as_deref(), Some("new"));
+        assert_eq!(commit_file(&repo, outcome.commit, "mission.txt").as_deref(), Some("new"));
+
+        // Reported paths outside of the repo are neither committed nor touched
+        assert_eq!(fs::read_to_string(dir.path().join("outside.txt")).unwrap(), "outside\n");
+        assert!(repo.statuses(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_command_rolled_back() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[("mission.txt", "old"), ("index.txt", "old index")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let parent = head_of(&repo);
+        register_script(&repo, dir.path(), "TestFailIndex", 1);
+
+        assert!(matches!(publish_with(&repo, "TestFailIndex", "new"), Err(RepoError::CommandFailed { .. })));
+        assert_eq!(head_of(&repo), parent);
+        assert_eq!(read_workdir(&repo, "index.txt").as_deref(), Some("old index"));
+        assert_eq!(read_workdir(&repo, "mission.txt").as_deref(), Some("old"));
+
+        // Only files within the repo are rolled back
+        assert_eq!(fs::read_to_string(dir.path().join("outside.txt")).unwrap(), "outside\n");
+    }
+}
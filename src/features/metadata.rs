@@ -0,0 +1,230 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature, MissionContext};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Metadata";
+
+const MISSION_MANIFEST_SUFFIX: &'static str = ".manifest.json";
+
+const METADATA_FIELD_DELIMIT: char = ';';
+const METADATA_VALUE_DELIMIT: char = ':';
+const METADATA_ESCAPE: char = '\\';
+
+/// Describes a mission for tooling which indexes gists, passed as `Metadata=name:<name>;author:<author>;description:<description>;engine:<engine version>`
+///
+/// Every field is optional, and `;`, `:` or `\` within a value must be escaped with a preceding `\`
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MissionMetadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub engine_version: Option<String>
+}
+
+/// Splits the string on every unescaped occurrence of the delimiter, removing escapes from the result
+fn split_escaped(s: &str, delimit: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            METADATA_ESCAPE => match chars.next() {
+                Some(escaped) => parts.last_mut().unwrap().push(escaped),
+                None => parts.last_mut().unwrap().push(METADATA_ESCAPE)
+            },
+            c if c == delimit => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c)
+        };
+    }
+
+    return parts;
+}
+
+fn escape_metadata_value(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == METADATA_ESCAPE || c == METADATA_FIELD_DELIMIT || c == METADATA_VALUE_DELIMIT {
+            escaped.push(METADATA_ESCAPE);
+        }
+        escaped.push(c);
+    }
+
+    return escaped;
+}
+
+impl std::str::FromStr for MissionMetadata {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut metadata = MissionMetadata::default();
+
+        // Split fields before values so escaped delimiters within values are preserved for the second split
+        let mut fields = Vec::<String>::new();
+        let mut field = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                METADATA_ESCAPE => {
+                    field.push(c);
+                    match chars.next() {
+                        Some(escaped) => field.push(escaped),
+                        None => ()
+                    };
+                },
+                METADATA_FIELD_DELIMIT => fields.push(std::mem::take(&mut field)),
+                c => field.push(c)
+            };
+        }
+        fields.push(field);
+
+        for field in fields.iter().filter(|f| { !f.is_empty() }) {
+            let mut key_value = split_escaped(field, METADATA_VALUE_DELIMIT);
+            if key_value.len() != 2 {
+                return Err(format!("metadata field \"{}\" was not of the form key:value", field));
+            }
+
+            let value = key_value.pop();
+            let target = match key_value[0].as_str() {
+                "name" => &mut metadata.name,
+                "author" => &mut metadata.author,
+                "description" => &mut metadata.description,
+                "engine" => &mut metadata.engine_version,
+                key => return Err(format!("metadata key \"{}\" is unknown, expected one of name, author, description or engine", key))
+            };
+            *target = value;
+        }
+
+        return Ok(metadata);
+    }
+}
+
+impl Display for MissionMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("name", &self.name),
+            ("author", &self.author),
+            ("description", &self.description),
+            ("engine", &self.engine_version)
+        ];
+
+        let mut first = true;
+        for (key, value) in fields {
+            let value = match value {
+                Some(v) => v,
+                None => continue
+            };
+
+            if !first { f.write_fmt(format_args!("{}", METADATA_FIELD_DELIMIT))?; }
+            f.write_fmt(format_args!("{}{}{}", key, METADATA_VALUE_DELIMIT, escape_metadata_value(value)))?;
+            first = false;
+        }
+
+        return Ok(());
+    }
+}
+
+/// Mission metadata written as a JSON manifest next to the gist file on every publish
+pub struct Metadata {
+    metadata: MissionMetadata,
+    mission_file: String,
+    version_tag: Option<String>
+}
+
+impl Metadata {
+    fn manifest_file(&self) -> String {
+        return format!("{}{}", self.mission_file, MISSION_MANIFEST_SUFFIX);
+    }
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let arg = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Metadata {
+        metadata: features::parse_feature_arg::<MissionMetadata>(NAME, arg)?,
+        mission_file: String::new(),
+        version_tag: None
+    }));
+}
+
+impl CodelessFeature for Metadata {
+    fn name(&self) -> &str { return NAME; }
+    fn publishes_content(&self) -> bool { return true; }
+
+    fn attach(&mut self, mission: &MissionContext) {
+        self.mission_file = mission.gist_file.to_string();
+        self.version_tag = mission.version_tag.map(|t| { t.to_string() });
+    }
+}
+
+impl Display for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[{}]", NAME, self.metadata.name.as_deref().unwrap_or("Unnamed")));
+    }
+}
+
+impl RepoItem for Metadata {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Metadata {
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        let manifest = serde_json::json!({
+            "name": self.metadata.name,
+            "author": self.metadata.author,
+            "description": self.metadata.description,
+            "engine_version": self.metadata.engine_version,
+            "mission_version": self.version_tag,
+            "mission_file": self.mission_file
+        });
+
+        let manifest = match serde_json::to_string_pretty(&manifest) {
+            Ok(m) => m,
+            Err(e) => return Err(RepoError::PublishError(format!("failed to serialize mission manifest with error {}", e)))
+        };
+
+        files.overwrite_file(&self.manifest_file(), &format!("{}\n", manifest))?;
+        return Ok(());
+    }
+
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> {
+        return features::repo_file_valid(files, &self.manifest_file());
+    }
+}
+
+impl RepoDerivable for Metadata {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository;
+
+    #[test]
+    fn manifest_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+
+        let mut feature = construct(Some("name:Heist;description:a\\;b")).unwrap();
+        feature.attach(&MissionContext { gist_file: "missions/heist.txt", payload: "", version_tag: Some("1.2.0") });
+        feature.repo_publish(&mut files).unwrap();
+
+        let manifest = serde_json::from_str::<serde_json::Value>(&files.read_file("missions/heist.txt.manifest.json").unwrap()).unwrap();
+        assert_eq!(manifest, serde_json::json!({
+            "name": "Heist",
+            "author": null,
+            "description": "a;b",
+            "engine_version": null,
+            "mission_version": "1.2.0",
+            "mission_file": "missions/heist.txt"
+        }));
+    }
+}
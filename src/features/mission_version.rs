@@ -0,0 +1,221 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::features::{self, CodelessFeature, FeatureVersion, MissionContext};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "MissionVersion";
+
+/// Maps each gist file to its mission version, so missions sharing a repo don't bump each other's versions
+const MISSION_VERSION_TABLE_FILE: &'static str = ".custommissionversions.json";
+
+/// Repo-wide counter used before versions were tracked per file
+/// Folded into the version table by the first publish or migrate, which then removes it
+const LEGACY_MISSION_VERSION_FILE: &'static str = ".custommissionversion";
+
+/// Mission version counter of the gist file, incremented on every publish unless pinned to a specific version by the mission code
+pub struct MissionVersion {
+    version: u64,
+    pinned: Option<u64>,
+    mission_file: String,
+    versions: BTreeMap<String, u64>
+}
+
+fn read_version_table(files: &RepoFiles) -> Result<BTreeMap<String, u64>, RepoError> {
+    if !files.file_exists(MISSION_VERSION_TABLE_FILE)? { return Ok(BTreeMap::new()); }
+
+    let table_string = files.read_file(MISSION_VERSION_TABLE_FILE)?;
+    return match serde_json::from_str::<BTreeMap<String, u64>>(&table_string) {
+        Ok(t) => Ok(t),
+        Err(e) => Err(RepoError::DeriveError(format!("{} file did not contain a valid table of file versions with error {}", MISSION_VERSION_TABLE_FILE, e)))
+    };
+}
+
+fn write_version_table(files: &mut RepoFiles, versions: &BTreeMap<String, u64>) -> Result<(), RepoError> {
+    let table = match serde_json::to_string_pretty(versions) {
+        Ok(t) => t,
+        Err(e) => return Err(RepoError::PublishError(format!("failed to serialize mission version table with error {}", e)))
+    };
+
+    return files.overwrite_file(MISSION_VERSION_TABLE_FILE, &format!("{}\n", table));
+}
+
+/// Version of a file missing from the version table, taken from the legacy counter if the repo still has one
+fn read_legacy_version(files: &RepoFiles) -> Result<u64, RepoError> {
+    if !files.file_exists(LEGACY_MISSION_VERSION_FILE)? { return Ok(0); }
+
+    let version_string = files.read_file(LEGACY_MISSION_VERSION_FILE)?;
+    return match version_string.trim().parse::<u64>() {
+        Ok(rv) => Ok(rv),
+        Err(e) => Err(RepoError::DeriveError(format!("{} file did not contain valid u64 with error {}", LEGACY_MISSION_VERSION_FILE, e)))
+    };
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let pinned = match arg {
+        Some(arg) => Some(features::parse_feature_arg::<u64>(NAME, arg)?),
+        None => None
+    };
+
+    return Ok(Box::new(MissionVersion { version: 0, pinned: pinned, mission_file: String::new(), versions: BTreeMap::new() }));
+}
+
+impl CodelessFeature for MissionVersion {
+    fn name(&self) -> &str { return NAME; }
+    fn version(&self) -> Option<FeatureVersion> { return Some(FeatureVersion::Revision(self.version)); }
+
+    fn attach(&mut self, mission: &MissionContext) {
+        self.mission_file = mission.gist_file.to_string();
+    }
+
+    /// Records the legacy repo-wide counter as the version of the gist file in the version table
+    fn repo_migrate(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> {
+        if !files.file_exists(LEGACY_MISSION_VERSION_FILE)? { return Ok(Vec::new()); }
+
+        let mut versions = read_version_table(files)?;
+        if versions.contains_key(&self.mission_file) { return Ok(Vec::new()); }
+
+        let version = read_legacy_version(files)?;
+        versions.insert(self.mission_file.clone(), version);
+        if !dry_run { write_version_table(files, &versions)?; }
+
+        return Ok(vec![format!("{}: recorded {} at version {} from {}", MISSION_VERSION_TABLE_FILE, self.mission_file, version, LEGACY_MISSION_VERSION_FILE)]);
+    }
+
+    /// Removes the legacy counter once every migrated gist file has been recorded from it
+    fn repo_retire(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> {
+        if !files.file_exists(LEGACY_MISSION_VERSION_FILE)? { return Ok(Vec::new()); }
+
+        if !dry_run { files.remove_file(LEGACY_MISSION_VERSION_FILE)?; }
+        return Ok(vec![format!("{}: removed, files missing from {} now start at version 1", LEGACY_MISSION_VERSION_FILE, MISSION_VERSION_TABLE_FILE)]);
+    }
+}
+
+impl Display for MissionVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self.pinned {
+            Some(v) => f.write_fmt(format_args!("{}[={}]", NAME, v)),
+            None => f.write_str(NAME)
+        };
+    }
+}
+
+impl RepoItem for MissionVersion {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for MissionVersion {
+    /// Removes the legacy counter in the same commit, as its value now lives in the version table
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        let mut versions = self.versions.clone();
+        versions.insert(self.mission_file.clone(), self.version);
+        write_version_table(files, &versions)?;
+
+        files.remove_file(LEGACY_MISSION_VERSION_FILE)?;
+        return Ok(());
+    }
+
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> {
+        features::repo_file_valid(files, LEGACY_MISSION_VERSION_FILE)?;
+        return features::repo_file_valid(files, MISSION_VERSION_TABLE_FILE);
+    }
+}
+
+impl RepoDerivable for MissionVersion {
+    fn repo_init(&mut self, files: &mut RepoFiles) -> Result<Option<String>, RepoError> {
+        // Legacy repos are migrated into the table on their next publish instead
+        if files.file_exists(LEGACY_MISSION_VERSION_FILE)? { return Ok(None); }
+
+        return features::repo_file_init(files, MISSION_VERSION_TABLE_FILE, "{}");
+    }
+
+    fn repo_derive(&mut self, files: &RepoFiles) -> Result<(), RepoError> {
+        self.versions = read_version_table(files)?;
+        self.version = match self.versions.get(&self.mission_file) {
+            Some(v) => *v,
+            None => read_legacy_version(files)?
+        };
+
+        return Ok(());
+    }
+
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> {
+        self.version = self.pinned.unwrap_or(self.version + 1);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository;
+
+    fn attached(file: &str) -> MissionVersion {
+        let mut feature = MissionVersion { version: 0, pinned: None, mission_file: String::new(), versions: BTreeMap::new() };
+        feature.attach(&MissionContext { gist_file: file, payload: "", version_tag: None });
+        return feature;
+    }
+
+    /// Runs the feature through a publish of the file, without committing it
+    fn publish(files: &mut RepoFiles, file: &str) -> u64 {
+        let mut feature = attached(file);
+        assert_eq!(feature.repo_init(files).unwrap(), None);
+        feature.repo_derive(files).unwrap();
+        feature.repo_process(files).unwrap();
+        feature.repo_publish(files).unwrap();
+        return feature.version;
+    }
+
+    #[test]
+    fn legacy_counter_seeds_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+        files.overwrite_file(LEGACY_MISSION_VERSION_FILE, "5").unwrap();
+
+        assert_eq!(publish(&mut files, "a.txt"), 6);
+        assert!(!files.file_exists(LEGACY_MISSION_VERSION_FILE).unwrap());
+        assert!(files.written().contains(&String::from(LEGACY_MISSION_VERSION_FILE)));
+
+        // Files published once the legacy counter is retired start from scratch
+        assert_eq!(publish(&mut files, "b.txt"), 1);
+        assert_eq!(publish(&mut files, "a.txt"), 7);
+
+        let versions = read_version_table(&files).unwrap();
+        assert_eq!(versions, BTreeMap::from([(String::from("a.txt"), 7), (String::from("b.txt"), 1)]));
+    }
+
+    #[test]
+    fn legacy_counter_migrates() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+        files.overwrite_file(LEGACY_MISSION_VERSION_FILE, "5").unwrap();
+
+        assert_eq!(attached("a.txt").repo_migrate(&mut files, true).unwrap().len(), 1);
+        assert!(!files.file_exists(MISSION_VERSION_TABLE_FILE).unwrap());
+
+        assert_eq!(attached("a.txt").repo_migrate(&mut files, false).unwrap().len(), 1);
+        assert_eq!(attached("b.txt").repo_migrate(&mut files, false).unwrap().len(), 1);
+        assert!(attached("a.txt").repo_migrate(&mut files, false).unwrap().is_empty());
+
+        assert_eq!(attached("a.txt").repo_retire(&mut files, true).unwrap().len(), 1);
+        assert!(files.file_exists(LEGACY_MISSION_VERSION_FILE).unwrap());
+        assert_eq!(attached("a.txt").repo_retire(&mut files, false).unwrap().len(), 1);
+        assert!(!files.file_exists(LEGACY_MISSION_VERSION_FILE).unwrap());
+        assert!(attached("b.txt").repo_retire(&mut files, false).unwrap().is_empty());
+
+        let versions = read_version_table(&files).unwrap();
+        assert_eq!(versions, BTreeMap::from([(String::from("a.txt"), 5), (String::from("b.txt"), 5)]));
+
+        // Migrated files continue from the legacy counter rather than restarting, while new files start from scratch
+        assert_eq!(publish(&mut files, "a.txt"), 6);
+        assert_eq!(publish(&mut files, "c.txt"), 1);
+    }
+}
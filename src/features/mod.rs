@@ -0,0 +1,292 @@
+use std::{fmt::Display, sync::RwLock};
+
+use git2::Repository;
+
+use crate::mission_codes::{MissionCodeParseError, MissionFile};
+use crate::payload::PayloadEncoding;
+use crate::repo_management::{PublishTarget, RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub mod author;
+pub mod changelog;
+pub mod checksum;
+pub mod encoding;
+pub mod external;
+pub mod metadata;
+pub mod mission_version;
+pub mod notes;
+pub mod semver;
+pub mod unknown;
+
+use author::CommitAuthor;
+use semver::SemanticVersion;
+
+const FEATURE_ARG_DELIMIT: &'static str = "=";
+
+/// A mission version tracked by a feature
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeatureVersion {
+    Revision(u64),
+    Semantic(SemanticVersion)
+}
+
+/// State of the mission code a feature belongs to
+pub struct MissionContext<'a> {
+    pub gist_file: &'a str,
+
+    /// Decoded mission data of gist_file
+    pub payload: &'a str,
+
+    /// Display form of the mission version, only present once every feature has been processed
+    pub version_tag: Option<&'a str>
+}
+
+/// A feature of a codeless mission code, constructed by name through the feature registry
+pub trait CodelessFeature : RepoPublishable + RepoDerivable + Display {
+    /// Name of the feature as it appears within a mission code
+    fn name(&self) -> &str;
+
+    /// Argument of the feature as it appears within a mission code, if it has one
+    /// Registered features are given the argument they were constructed from by `construct`
+    fn argument(&self) -> Option<String> { return None; }
+
+    /// True only for features that aren't registered
+    fn is_unknown(&self) -> bool { return false; }
+
+    /// Mission version tracked by the feature, only meaningful once derived & processed
+    fn version(&self) -> Option<FeatureVersion> { return None; }
+
+    /// Encoding the feature declares for the mission data
+    fn payload_encoding(&self) -> Option<PayloadEncoding> { return None; }
+
+    /// Identity the feature declares as the author of the publish commit
+    fn commit_author(&self) -> Option<&CommitAuthor> { return None; }
+
+    /// Notes the feature adds to the body of the publish commit message
+    fn commit_notes(&self) -> Option<&str> { return None; }
+
+    /// Whether the feature publishes content of its own, rather than only tracking the mission data
+    fn publishes_content(&self) -> bool { return false; }
+
+    /// Checks the undecoded data of every file once the mission code is parsed, before anything is decoded or written
+    #[allow(unused_variables)] // should only be unused in default implementation
+    fn verify_files(&self, files: &[MissionFile]) -> Result<(), MissionCodeParseError> { return Ok(()); }
+
+    /// Rewrites repo state left by older versions of the feature into its current layout
+    /// Returns a description of every change, which are only written if not a dry run
+    #[allow(unused_variables)] // should only be unused in default implementation
+    fn repo_migrate(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> { return Ok(Vec::new()); }
+
+    /// Removes repo state left by older versions of the feature, once every mission code has been migrated
+    /// Returns a description of every change, which are only written if not a dry run
+    #[allow(unused_variables)] // should only be unused in default implementation
+    fn repo_retire(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> { return Ok(Vec::new()); }
+
+    /// Called once the mission code is parsed, and again once every feature has been processed
+    #[allow(unused_variables)] // should only be unused in default implementation
+    fn attach(&mut self, mission: &MissionContext) { }
+
+    /// The feature as it appears within a mission code
+    fn to_code_str(&self) -> String {
+        return match self.argument() {
+            Some(arg) => format!("{}{}{}", self.name(), FEATURE_ARG_DELIMIT, arg),
+            None => self.name().to_string()
+        };
+    }
+}
+
+/// Constructs a feature from its mission code argument
+pub type FeatureConstructor = Box<dyn Fn(Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> + Send + Sync>;
+
+struct FeatureRegistration {
+    name: String,
+    constructor: FeatureConstructor
+}
+
+static FEATURE_REGISTRY: RwLock<Vec<FeatureRegistration>> = RwLock::new(Vec::new());
+
+/// Registers a feature under the given name, replacing any feature already registered under it
+pub fn register(name: &str, constructor: FeatureConstructor) {
+    let mut registry = FEATURE_REGISTRY.write().unwrap();
+    match registry.iter_mut().find(|r| { r.name == name }) {
+        Some(existing) => existing.constructor = constructor,
+        None => registry.push(FeatureRegistration { name: name.to_string(), constructor: constructor })
+    };
+}
+
+/// Registers every feature built into this program
+pub fn register_builtin() {
+    register(mission_version::NAME, Box::new(mission_version::construct));
+    register(semver::NAME, Box::new(semver::construct));
+    register(changelog::NAME, Box::new(changelog::construct));
+    register(metadata::NAME, Box::new(metadata::construct));
+    register(encoding::NAME, Box::new(encoding::construct));
+    register(author::NAME, Box::new(author::construct));
+    register(notes::NAME, Box::new(notes::construct));
+    register(checksum::NAME, Box::new(checksum::construct));
+}
+
+/// Names of every registered feature, in registration order
+pub fn registered_names() -> Vec<String> {
+    return FEATURE_REGISTRY.read().unwrap().iter().map(|r| { r.name.clone() }).collect();
+}
+
+/// Constructs a feature from its mission code form, features which aren't registered are kept as unknown features
+pub fn construct(feature_str: &str) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let (feature_name, feature_arg) = match feature_str.split_once(FEATURE_ARG_DELIMIT) {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (feature_str, None)
+    };
+
+    let registry = FEATURE_REGISTRY.read().unwrap();
+    return match registry.iter().find(|r| { r.name == feature_name }) {
+        Some(registration) => Ok(Box::new(ConstructedFeature {
+            feature: (registration.constructor)(feature_arg)?,
+            argument: feature_arg.map(|a| { a.to_string() })
+        })),
+        None => Ok(Box::new(unknown::UnknownFeature::new(feature_name, feature_arg)))
+    };
+}
+
+/// A registered feature along with the argument it was constructed from, so the mission code re-serializes unchanged
+/// Everything else is passed through to the feature
+struct ConstructedFeature {
+    feature: Box<dyn CodelessFeature>,
+    argument: Option<String>
+}
+
+impl CodelessFeature for ConstructedFeature {
+    fn name(&self) -> &str { return self.feature.name(); }
+    fn argument(&self) -> Option<String> { return self.argument.clone(); }
+    fn is_unknown(&self) -> bool { return self.feature.is_unknown(); }
+    fn version(&self) -> Option<FeatureVersion> { return self.feature.version(); }
+    fn payload_encoding(&self) -> Option<PayloadEncoding> { return self.feature.payload_encoding(); }
+    fn commit_author(&self) -> Option<&CommitAuthor> { return self.feature.commit_author(); }
+    fn commit_notes(&self) -> Option<&str> { return self.feature.commit_notes(); }
+    fn publishes_content(&self) -> bool { return self.feature.publishes_content(); }
+
+    fn verify_files(&self, files: &[MissionFile]) -> Result<(), MissionCodeParseError> {
+        return self.feature.verify_files(files);
+    }
+
+    fn repo_migrate(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> {
+        return self.feature.repo_migrate(files, dry_run);
+    }
+
+    fn repo_retire(&self, files: &mut RepoFiles, dry_run: bool) -> Result<Vec<String>, RepoError> {
+        return self.feature.repo_retire(files, dry_run);
+    }
+
+    fn attach(&mut self, mission: &MissionContext) {
+        self.feature.attach(mission);
+    }
+}
+
+impl Display for ConstructedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return self.feature.fmt(f);
+    }
+}
+
+impl RepoItem for ConstructedFeature {
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return self.feature.publishable_children();
+    }
+
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return self.feature.derivable_children();
+    }
+}
+
+impl RepoPublishable for ConstructedFeature {
+    fn publish_message(&self) -> String { return self.feature.publish_message(); }
+
+    fn publish_target_remote(&self, repo: &Repository) -> Result<String, RepoError> {
+        return self.feature.publish_target_remote(repo);
+    }
+
+    fn publish_targets(&self) -> Result<Vec<PublishTarget>, RepoError> { return self.feature.publish_targets(); }
+    fn publish_extras(&self) -> Vec<String> { return self.feature.publish_extras(); }
+
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> { return self.feature.repo_publish(files); }
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> { return self.feature.repo_valid(files); }
+}
+
+impl RepoDerivable for ConstructedFeature {
+    fn repo_init(&mut self, files: &mut RepoFiles) -> Result<Option<String>, RepoError> { return self.feature.repo_init(files); }
+    fn repo_derive(&mut self, files: &RepoFiles) -> Result<(), RepoError> { return self.feature.repo_derive(files); }
+    fn repo_process(&mut self, files: &RepoFiles) -> Result<(), RepoError> { return self.feature.repo_process(files); }
+}
+
+/// Every registered feature, constructed with its default arguments
+/// Features which require an argument are skipped
+pub fn construct_all_registered() -> Vec<Box<dyn CodelessFeature>> {
+    let registry = FEATURE_REGISTRY.read().unwrap();
+    return registry.iter().filter_map(|r| { (r.constructor)(None).ok() }).collect();
+}
+
+/// Levenshtein distance between two strings, compared case-insensitively
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len()+1];
+
+    for i in 1..=a.len() {
+        row[0] = i;
+        for j in 1..=b.len() {
+            let substitution_cost = if a[i-1] == b[j-1] { 0 } else { 1 };
+            row[j] = (prev_row[j] + 1).min(row[j-1] + 1).min(prev_row[j-1] + substitution_cost);
+        }
+        std::mem::swap(&mut row, &mut prev_row);
+    }
+
+    return prev_row[b.len()];
+}
+
+/// Returns the registered feature closest to the given name, provided it is close enough to plausibly be a typo
+pub fn suggest_registered(feature_name: &str) -> Option<String> {
+    let max_distance = (feature_name.len() / 3).max(2);
+
+    return registered_names().into_iter()
+        .map(|known| { let distance = edit_distance(feature_name, &known); (known, distance) })
+        .filter(|(_, distance)| { *distance <= max_distance })
+        .min_by_key(|(_, distance)| { *distance })
+        .map(|(known, _)| { known });
+}
+
+fn parse_feature_arg<T>(feature: &str, arg: &str) -> Result<T, MissionCodeParseError>
+    where T: std::str::FromStr, T::Err: Display
+{
+    return match arg.parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(MissionCodeParseError::FeatureArgumentInvalid {
+            feature: feature.to_string(),
+            argument: arg.to_string(),
+            reason: e.to_string()
+        })
+    };
+}
+
+fn required_feature_arg<'a>(feature: &str, arg: Option<&'a str>) -> Result<&'a str, MissionCodeParseError> {
+    return match arg {
+        Some(arg) => Ok(arg),
+        None => Err(MissionCodeParseError::FeatureArgumentMissing(feature.to_string()))
+    };
+}
+
+/// Writes the default contents to the given file if it doesn't already exist
+fn repo_file_init(files: &mut RepoFiles, file: &str, default_contents: &str) -> Result<Option<String>, RepoError> {
+    if files.file_exists(file)? { return Ok(None); }
+
+    files.overwrite_file(file, default_contents)?;
+    return Ok(Some(format!("{} with contents \"{}\"", file, default_contents)));
+}
+
+/// Errors if a non-file item exists at the given path within the repo
+fn repo_file_valid(files: &RepoFiles, file: &str) -> Result<(), RepoError> {
+    return match files.non_file_exists(file)? {
+        true => Err(RepoError::PublishError(format!("non-file item already exists at {}", file))),
+        false => Ok(())
+    };
+}
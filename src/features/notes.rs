@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "Notes";
+
+/// Free-text release notes appended to the body of the publish commit message
+pub struct Notes(String);
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let notes = features::required_feature_arg(NAME, arg)?;
+    return Ok(Box::new(Notes(notes.to_string())));
+}
+
+impl CodelessFeature for Notes {
+    fn name(&self) -> &str { return NAME; }
+    fn commit_notes(&self) -> Option<&str> { return Some(&self.0); }
+    fn publishes_content(&self) -> bool { return true; }
+}
+
+impl Display for Notes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(NAME);
+    }
+}
+
+impl RepoItem for Notes {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for Notes {
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for Notes {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
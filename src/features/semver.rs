@@ -0,0 +1,151 @@
+use std::fmt::Display;
+
+use crate::features::{self, CodelessFeature, FeatureVersion};
+use crate::mission_codes::MissionCodeParseError;
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+pub const NAME: &'static str = "SemVer";
+
+const MISSION_SEMVER_FILE: &'static str = ".custommissionsemver";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SemVerBump {
+    Major,
+    Minor,
+    Patch
+}
+
+impl std::str::FromStr for SemVerBump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.to_lowercase().as_str() {
+            "major" => Ok(SemVerBump::Major),
+            "minor" => Ok(SemVerBump::Minor),
+            "patch" => Ok(SemVerBump::Patch),
+            _ => Err(String::from("expected one of major, minor or patch"))
+        };
+    }
+}
+
+impl Display for SemVerBump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(match self {
+            SemVerBump::Major => "major",
+            SemVerBump::Minor => "minor",
+            SemVerBump::Patch => "patch"
+        });
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SemanticVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64
+}
+
+impl SemanticVersion {
+    pub fn bump(&mut self, bump: SemVerBump) {
+        match bump {
+            SemVerBump::Major => { self.major += 1; self.minor = 0; self.patch = 0; },
+            SemVerBump::Minor => { self.minor += 1; self.patch = 0; },
+            SemVerBump::Patch => { self.patch += 1; }
+        };
+    }
+}
+
+impl std::str::FromStr for SemanticVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        if parts.len() != 3 {
+            return Err(format!("expected version of form major.minor.patch, got \"{}\"", s));
+        }
+
+        let mut numbers = [0_u64; 3];
+        for (i, part) in parts.iter().enumerate() {
+            numbers[i] = match part.parse::<u64>() {
+                Ok(n) => n,
+                Err(e) => return Err(format!("version component \"{}\" was not a valid u64 with error {}", part, e))
+            };
+        }
+
+        return Ok(SemanticVersion { major: numbers[0], minor: numbers[1], patch: numbers[2] });
+    }
+}
+
+impl Display for SemanticVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}.{}.{}", self.major, self.minor, self.patch));
+    }
+}
+
+/// Semantic mission version, bumped at the level given by the mission code on every publish, defaulting to a patch bump
+pub struct SemVer {
+    version: SemanticVersion,
+    bump: SemVerBump
+}
+
+pub fn construct(arg: Option<&str>) -> Result<Box<dyn CodelessFeature>, MissionCodeParseError> {
+    let bump = match arg {
+        Some(arg) => features::parse_feature_arg::<SemVerBump>(NAME, arg)?,
+        None => SemVerBump::Patch
+    };
+
+    return Ok(Box::new(SemVer { version: SemanticVersion::default(), bump: bump }));
+}
+
+impl CodelessFeature for SemVer {
+    fn name(&self) -> &str { return NAME; }
+    fn version(&self) -> Option<FeatureVersion> { return Some(FeatureVersion::Semantic(self.version)); }
+}
+
+impl Display for SemVer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{}[{}]", NAME, self.bump));
+    }
+}
+
+impl RepoItem for SemVer {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for SemVer {
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        files.overwrite_file(MISSION_SEMVER_FILE, &self.version.to_string())?;
+        return Ok(());
+    }
+
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> {
+        return features::repo_file_valid(files, MISSION_SEMVER_FILE);
+    }
+}
+
+impl RepoDerivable for SemVer {
+    fn repo_init(&mut self, files: &mut RepoFiles) -> Result<Option<String>, RepoError> {
+        return features::repo_file_init(files, MISSION_SEMVER_FILE, &SemanticVersion::default().to_string());
+    }
+
+    fn repo_derive(&mut self, files: &RepoFiles) -> Result<(), RepoError> {
+        let version_string = files.read_file(MISSION_SEMVER_FILE)?;
+        self.version = match version_string.parse::<SemanticVersion>() {
+            Ok(rv) => rv,
+            Err(e) => return Err(RepoError::DeriveError(format!("{} file did not contain valid semantic version with error {}", MISSION_SEMVER_FILE, e)))
+        };
+
+        return Ok(());
+    }
+
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> {
+        self.version.bump(self.bump);
+        return Ok(());
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt::Display;
+
+use crate::features::{CodelessFeature, FEATURE_ARG_DELIMIT};
+use crate::repo_management::{RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
+
+/// A feature not understood by this program, kept so it survives re-serialization of the mission code
+pub struct UnknownFeature {
+    name: String,
+    argument: Option<String>
+}
+
+impl UnknownFeature {
+    pub fn new(name: &str, argument: Option<&str>) -> Self {
+        return UnknownFeature { name: name.to_string(), argument: argument.map(|a| { a.to_string() }) };
+    }
+}
+
+impl CodelessFeature for UnknownFeature {
+    fn name(&self) -> &str { return &self.name; }
+    fn argument(&self) -> Option<String> { return self.argument.clone(); }
+    fn is_unknown(&self) -> bool { return true; }
+}
+
+impl Display for UnknownFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match &self.argument {
+            Some(arg) => f.write_fmt(format_args!("Unknown[{}{}{}]", self.name, FEATURE_ARG_DELIMIT, arg)),
+            None => f.write_fmt(format_args!("Unknown[{}]", self.name))
+        };
+    }
+}
+
+impl RepoItem for UnknownFeature {
+    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
+        return None;
+    }
+
+    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
+        return None;
+    }
+}
+
+impl RepoPublishable for UnknownFeature {
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
+
+impl RepoDerivable for UnknownFeature {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+}
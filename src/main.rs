@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::{path::{Path, PathBuf}, sync::Arc};
 
 use clap::Parser;
 
@@ -8,9 +8,12 @@ mod program_info;
 mod cmterm;
 
 mod clipboard;
+mod commands;
 mod server;
 mod repo_management;
 mod mission_codes;
+mod features;
+mod payload;
 
 use main_err::MainErr;
 use program_info::{ProgramArgs, ProgramInfo};
@@ -158,6 +161,14 @@ fn main() {
     #[cfg(target_os="linux")]
     if args.linux_clipboard_daemon.is_some() { return }
 
+    features::register_builtin();
+
+    if let Some(command) = args.command.as_ref() {
+        let command_log = Arc::new(cmterm::Log::new_headless("Main Thread"));
+        cmterm::Log::set(command_log.clone());
+        std::process::exit(commands::run(command, &args, &command_log));
+    }
+
     let term_man = cmterm::Manager::new();
     let main_log = term_man.main_log.clone();
     let server_log = term_man.server_log.clone();
@@ -1,11 +1,21 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, path::{Component, Path}};
 
 use git2::Repository;
 
-use crate::repo_management::{self, RepoDerivable, RepoError, RepoItem, RepoPublishable};
+use crate::cmterm;
+use crate::features::{self, CodelessFeature, FeatureVersion, MissionContext};
+use crate::payload::{PayloadDecodeError, PayloadEncoding};
+use crate::repo_management::{self, PublishTarget, RepoDerivable, RepoError, RepoFiles, RepoItem, RepoPublishable};
 
 const CODELESS_CM_IDENTIFIER: &'static str = "_infilengine_cm_codeless_";
 const CODELESS_ELEM_DELIMIT: &'static str = "|";
+const CODELESS_ELEM_LENGTH_DELIMIT: &'static str = ":";
+
+const MISSION_INDEX_SUFFIX: &'static str = ".index";
+const MISSION_PART_SUFFIX: &'static str = ".part";
+
+/// The maximum size in bytes of a single mission code, files larger than this are split into numbered parts when published
+pub const MAX_CODE_SIZE: usize = 200_000;
 
 fn next_code_elem(code: &str, fail_err: MissionCodeParseError) -> Result<(&str, &str), MissionCodeParseError> {
     return match code.split_once(CODELESS_ELEM_DELIMIT) {
@@ -14,24 +24,74 @@ fn next_code_elem(code: &str, fail_err: MissionCodeParseError) -> Result<(&str,
     };
 }
 
+/// Reads a single length-prefixed element of the form `<byte length>:<element>`, followed by a delimiter unless it is the final element
+fn next_code_elem_prefixed(code: &str, fail_err: MissionCodeParseError) -> Result<(&str, &str), MissionCodeParseError> {
+    let (elem, code) = split_code_elem_prefixed(code, fail_err)?;
+    let code = match code.strip_prefix(CODELESS_ELEM_DELIMIT) {
+        Some(c) => c,
+        None if code.is_empty() => code,
+        None => return Err(MissionCodeParseError::ElementUnterminated)
+    };
+
+    return Ok((elem, code));
+}
+
+/// Reads a single length-prefixed element of the form `<byte length>:<element>`, leaving any following delimiter in the remaining code
+fn split_code_elem_prefixed(code: &str, fail_err: MissionCodeParseError) -> Result<(&str, &str), MissionCodeParseError> {
+    if code.is_empty() { return Err(fail_err) }
+
+    let (elem_len, code) = match code.split_once(CODELESS_ELEM_LENGTH_DELIMIT) {
+        Some(s) => s,
+        None => return Err(MissionCodeParseError::ElementLengthMissing)
+    };
+
+    let elem_len = match elem_len.parse::<usize>() {
+        Ok(l) => l,
+        Err(_) => return Err(MissionCodeParseError::ElementLengthInvalid)
+    };
+
+    if code.len() < elem_len || !code.is_char_boundary(elem_len) {
+        return Err(MissionCodeParseError::ElementTruncated);
+    }
+
+    return Ok(code.split_at(elem_len));
+}
+
 #[derive(Debug)]
 pub enum MissionCodeParseError {
     CodelessVersionUnknown(usize),
     CodelessVersionMissing,
     CodelessVersionNotUInt,
 
+    ElementLengthMissing,
+    ElementLengthInvalid,
+    ElementTruncated,
+    ElementUnterminated,
+    TrailingData,
+
     FeatureCountMissing,
     FeatureCountInvalid,
     FeatureMissing,
+    FeatureArgumentMissing(String),
+    FeatureArgumentInvalid{feature: String, argument: String, reason: String},
+    FeatureUnknown{feature: String, suggestion: Option<String>},
 
     GistFileMissing,
+    FileCountMissing,
+    FileCountInvalid,
+    FileNameInvalid(String),
+    FileNameDuplicate(String),
     GistRemoteMissing,
     GistURLMissing,
+    CodeDataMissing,
 
     HasBothGistRemoteAndURL,
     HasNoGistRemoteOrURL,
 
     InputWasntCode,
+
+    PayloadDecodeFailed(PayloadDecodeError),
+    ChecksumMismatch{expected: String, actual: String},
 }
 
 impl Display for MissionCodeParseError { 
@@ -40,33 +100,106 @@ impl Display for MissionCodeParseError {
             Self::CodelessVersionUnknown(v) => f.write_fmt(format_args!("input strings version \'{}\' is not supported", v)),
             Self::CodelessVersionMissing => f.write_str("input string is missing codeless version"),
             Self::CodelessVersionNotUInt => f.write_str("input strings version was not a uint"),
+
+            Self::ElementLengthMissing => f.write_str("input string is missing an element length prefix"),
+            Self::ElementLengthInvalid => f.write_str("input string element length prefix wasn't valid usize"),
+            Self::ElementTruncated => f.write_str("input string element was shorter than its length prefix"),
+            Self::ElementUnterminated => f.write_str("input string element was longer than its length prefix"),
+            Self::TrailingData => f.write_str("input string has unexpected data after the mission code"),
             
             Self::FeatureCountMissing => f.write_str("input string is missing feature count"),
             Self::FeatureCountInvalid => f.write_str("input string feature count wasn't valid u64"),
             Self::FeatureMissing => f.write_str("input string is missing expected feature"),
+            Self::FeatureUnknown{feature, suggestion: Some(s)} => f.write_fmt(format_args!("input string feature {feature} is unknown, did you mean {s}?")),
+            Self::FeatureUnknown{feature, suggestion: None} => f.write_fmt(format_args!("input string feature {feature} is unknown")),
+            Self::FeatureArgumentMissing(feature) => f.write_fmt(format_args!("input string feature {feature} is missing its required argument")),
+            Self::FeatureArgumentInvalid{feature, argument, reason} => f.write_fmt(format_args!("input string feature {feature} has invalid argument \"{argument}\" with reason \"{reason}\"")),
 
             Self::GistFileMissing => f.write_str("input string is missing gist filename"),
+            Self::FileCountMissing => f.write_str("input string is missing file count"),
+            Self::FileCountInvalid => f.write_str("input string file count wasn't a valid non-zero usize"),
+            Self::FileNameInvalid(name) => f.write_fmt(format_args!("input string file name \"{name}\" is not a path within the repo")),
+            Self::FileNameDuplicate(name) => f.write_fmt(format_args!("input string has more than one file named \"{name}\"")),
             Self::GistRemoteMissing => f.write_str("input string is missing remote"),
             Self::GistURLMissing => f.write_str("input string is missing gist URL"),
+            Self::CodeDataMissing => f.write_str("input string is missing mission data"),
 
             Self::HasBothGistRemoteAndURL => f.write_str("input string has both a gist remote and a gist URL"),
             Self::HasNoGistRemoteOrURL => f.write_str("input string has neither a gist remote or gist URL"),
 
             Self::InputWasntCode => f.write_str("input string was not valid codeless mission"),
+
+            Self::PayloadDecodeFailed(e) => f.write_fmt(format_args!("input string mission data could not be decoded: {e}")),
+            Self::ChecksumMismatch{expected, actual} => f.write_fmt(format_args!("input string mission data has checksum {actual} but expected {expected}, it was likely truncated or mangled in transit")),
         }
     }
 }
 
 impl Error for MissionCodeParseError { }
 
+/// Version of the codeless wire format
+/// 
+/// V0 delimits every element with a bare `|`, so only the trailing mission data may safely contain one
+/// 
+/// V1 prefixes every element following the version with its length in bytes (`<length>:<element>|`), so any element may contain any character
+/// 
+/// V2 is encoded as V1, but ends with a table of named files (`<url>|<remote>|<file count>|<file>|<data>|<file>|<data>...`) in place of a single gist file & mission data
 pub enum CodelessInfo {
-    V0
+    V0,
+    V1,
+    V2
 }
 
 impl CodelessInfo {
+    /// The format version codes are upgraded to when migrated
+    pub const NEWEST: CodelessInfo = CodelessInfo::V2;
+
     pub fn version(&self) -> usize {
         return match self {
-            CodelessInfo::V0 => 0
+            CodelessInfo::V0 => 0,
+            CodelessInfo::V1 => 1,
+            CodelessInfo::V2 => 2
+        };
+    }
+
+    /// Splits the next element from the remaining code according to this format version
+    fn next_elem<'a>(&self, code: &'a str, fail_err: MissionCodeParseError) -> Result<(&'a str, &'a str), MissionCodeParseError> {
+        return match self {
+            CodelessInfo::V0 => next_code_elem(code, fail_err),
+            CodelessInfo::V1 | CodelessInfo::V2 => next_code_elem_prefixed(code, fail_err)
+        };
+    }
+
+    /// Appends an element to the code according to this format version
+    fn push_elem(&self, code: &mut String, elem: &str) {
+        self.push_final_elem(code, elem);
+        code.push_str(CODELESS_ELEM_DELIMIT);
+    }
+
+    /// Appends the final element to the code according to this format version
+    fn push_final_elem(&self, code: &mut String, elem: &str) {
+        match self {
+            CodelessInfo::V0 => (),
+            CodelessInfo::V1 | CodelessInfo::V2 => {
+                code.push_str(&elem.len().to_string());
+                code.push_str(CODELESS_ELEM_LENGTH_DELIMIT);
+            }
+        };
+        code.push_str(elem);
+    }
+
+    /// Reads the final element of the code, which must consume the remaining input
+    fn final_elem<'a>(&self, code: &'a str, fail_err: MissionCodeParseError) -> Result<&'a str, MissionCodeParseError> {
+        return match self {
+            CodelessInfo::V0 => Ok(code),
+            CodelessInfo::V1 | CodelessInfo::V2 => {
+                // The final element is never followed by a delimiter, so one left over is trailing data
+                let (elem, code) = split_code_elem_prefixed(code, fail_err)?;
+                match code.is_empty() {
+                    true => Ok(elem),
+                    false => Err(MissionCodeParseError::TrailingData)
+                }
+            }
         };
     }
 
@@ -79,6 +212,8 @@ impl CodelessInfo {
 
         let ci = match version {
             0 => CodelessInfo::V0,
+            1 => CodelessInfo::V1,
+            2 => CodelessInfo::V2,
             _ => return Err(MissionCodeParseError::CodelessVersionUnknown(version))
         };
 
@@ -97,133 +232,127 @@ impl RepoItem for CodelessInfo {
 }
 
 impl RepoPublishable for CodelessInfo {
-    fn repo_publish(&self, _repo: &Repository) -> Result<(), RepoError> { return Ok(()); }
-    fn repo_valid(&self, _repo: &Repository) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_publish(&self, _files: &mut RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+    fn repo_valid(&self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
 }
 
-pub enum CodelessRepoFeature {
-    UnknownFeature(String),
-    MissionVersion(u64)
+fn part_file_name(file: &str, part: usize) -> String {
+    return format!("{}{}{}", file, MISSION_PART_SUFFIX, part);
 }
 
-impl CodelessRepoFeature {
-    fn from_str(feature_str: &str) -> CodelessRepoFeature {
-        return match feature_str {
-            "MissionVersion" => Self::MissionVersion(0),
-            _ => Self::UnknownFeature(feature_str.to_string())
-        }
-    }
-}
+/// Splits data larger than MAX_CODE_SIZE into numbered part files, preceded by an index file listing the parts in order
+/// Returns the targets along with the number of part files, which is 0 if the data wasn't split
+fn split_publish_target(target: PublishTarget) -> (Vec<PublishTarget>, usize) {
+    if target.data.len() <= MAX_CODE_SIZE { return (vec![target], 0); }
 
-impl Display for CodelessRepoFeature {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return match self {
-            CodelessRepoFeature::MissionVersion(_) => f.write_str("MissionVersion"),
-            CodelessRepoFeature::UnknownFeature(s) => f.write_fmt(format_args!("Unknown[{}]", s))
-        }
+    let mut parts = Vec::<PublishTarget>::with_capacity(target.data.len() / MAX_CODE_SIZE + 2);
+    let mut index = String::new();
+    let mut data = target.data.as_str();
+    while !data.is_empty() {
+        let mut split_at = MAX_CODE_SIZE.min(data.len());
+        while !data.is_char_boundary(split_at) { split_at -= 1; }
+
+        let (part, remaining) = data.split_at(split_at);
+        let part_file = part_file_name(&target.file, parts.len()+1);
+        index.push_str(&part_file);
+        index.push('\n');
+
+        parts.push(PublishTarget { file: part_file, data: part.to_string() });
+        data = remaining;
     }
+
+    let part_count = parts.len();
+    parts.insert(0, PublishTarget { file: format!("{}{}", target.file, MISSION_INDEX_SUFFIX), data: index });
+    return (parts, part_count);
 }
 
-impl RepoItem for CodelessRepoFeature {
-    fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
-        return None;
-    }
+/// Removes any part files and index left over from a previous publish of the file which are no longer in use,
+/// along with the unsplit file itself once it has been split into parts
+fn remove_stale_parts(files: &mut RepoFiles, file: &str, part_count: usize) -> Result<(), RepoError> {
+    match part_count {
+        0 => files.remove_file(&format!("{}{}", file, MISSION_INDEX_SUFFIX))?,
+        _ => files.remove_file(file)?
+    };
 
-    fn publishable_children(&self) -> Option<Vec<&dyn RepoPublishable>> {
-        return None;
+    let mut part = part_count+1;
+    while files.remove_file(&part_file_name(file, part))? {
+        part += 1;
     }
+
+    return Ok(());
 }
 
-impl RepoPublishable for CodelessRepoFeature {
-    fn repo_publish(&self, repo: &Repository) -> Result<(), RepoError> {
-        return match self {
-            CodelessRepoFeature::MissionVersion(v) => {
-                repo_management::overwrite_file(repo, ".custommissionversion", &v.to_string())?;
-                Ok(())
-            }
-            CodelessRepoFeature::UnknownFeature(_f) => {
-                Ok(())
-            }
+/// Errors unless every file name is a distinct path within the repo, as names are joined onto the workdir when published
+fn verify_file_names(files: &[MissionFile]) -> Result<(), MissionCodeParseError> {
+    for (i, file) in files.iter().enumerate() {
+        let within_repo = Path::new(&file.name).components().all(|c| { matches!(c, Component::Normal(_)) });
+        if file.name.is_empty() || !within_repo {
+            return Err(MissionCodeParseError::FileNameInvalid(file.name.clone()));
         }
-    }
 
-    fn repo_valid(&self, repo: &Repository) -> Result<(), RepoError> {
-        return match self {
-            CodelessRepoFeature::MissionVersion(_) => {
-                let p = repo_management::get_repo_file_path(repo, ".custommissionversion")?;
-                match p.exists() && !p.is_file() {
-                    true => Err(RepoError::PublishError(format!("non-file item already exists at {}", p.to_string_lossy()))),
-                    false => Ok(())
-                }
-            }
-            CodelessRepoFeature::UnknownFeature(_) => Ok(())
+        if files[..i].iter().any(|f| { f.name == file.name }) {
+            return Err(MissionCodeParseError::FileNameDuplicate(file.name.clone()));
         }
     }
+
+    return Ok(());
 }
 
-impl RepoDerivable for CodelessRepoFeature {
-    fn repo_derive(&mut self, repo: &Repository) -> Result<(), RepoError> {
-        return match self {
-            CodelessRepoFeature::MissionVersion(v) => {
-                let version_string = repo_management::read_file(repo, ".custommissionversion")?;
-                *v = match version_string.parse::<u64>() {
-                    Ok(rv) => rv,
-                    Err(e) => return Err(RepoError::DeriveError(format!(".custommissionversion file did not contain valid u64 with error {}", e)))
-                };
-                Ok(())
-            }
-            CodelessRepoFeature::UnknownFeature(_) => Ok(())
-        }
-    }
+/// A named file published alongside the main gist file of a mission
+pub struct MissionFile {
+    pub name: String,
+    pub data: String,
 
-    fn repo_process(&mut self, _repo: &Repository) -> Result<(), RepoError> {
-        return match self {
-            CodelessRepoFeature::MissionVersion(v) => {
-                *v += 1;
-                Ok(())
-            }
-            CodelessRepoFeature::UnknownFeature(_) => Ok(())
-        }
-    }
+    /// data decoded with the payload encoding, filled in once the code has been verified
+    pub payload: String
 }
 
 pub struct MissionCode {
     pub codeless_fmt_version: CodelessInfo,
-    pub codeless_features: Vec<CodelessRepoFeature>,
+    pub codeless_features: Vec<Box<dyn CodelessFeature>>,
     pub gist_file: String,
     pub gist_url: Option<String>,
     pub gist_remote: Option<String>,
     pub code_data: String,
+
+    /// code_data decoded with the payload encoding
+    pub code_payload: String,
+
+    /// Files published in the same commit as gist_file, only representable from V2 onwards
+    pub extra_files: Vec<MissionFile>,
 }
 
 impl MissionCode {
     pub fn parse_from(code: &str) -> Result<MissionCode, MissionCodeParseError> {
-        if !code.starts_with(CODELESS_CM_IDENTIFIER) { return Err(MissionCodeParseError::InputWasntCode) }
-
-        // +1 for delimiter
-        let code = &code[CODELESS_CM_IDENTIFIER.len()+1..];
+        let code = match code.strip_prefix(CODELESS_CM_IDENTIFIER).and_then(|c| { c.strip_prefix(CODELESS_ELEM_DELIMIT) }) {
+            Some(c) => c,
+            None => return Err(MissionCodeParseError::InputWasntCode)
+        };
 
         let (codeless_info, code) = CodelessInfo::parse_from(code)?;
         
-        let (feature_count_str, code) = next_code_elem(code, MissionCodeParseError::FeatureCountMissing)?;
+        let (feature_count_str, code) = codeless_info.next_elem(code, MissionCodeParseError::FeatureCountMissing)?;
         let feature_count = match feature_count_str.parse::<usize>() {
             Ok(c) => c,
             Err(_) => return Err(MissionCodeParseError::FeatureCountInvalid)
         };
 
         let mut code = code;
-        let mut feature_vec = Vec::<CodelessRepoFeature>::with_capacity(feature_count);
+        let mut feature_vec = Vec::<Box<dyn CodelessFeature>>::new();
         for _ in 0..feature_count {
-            let (feature_str, code_slice) = next_code_elem(code, MissionCodeParseError::FeatureMissing)?;
+            let (feature_str, code_slice) = codeless_info.next_elem(code, MissionCodeParseError::FeatureMissing)?;
             code = code_slice;
 
-            let feature = CodelessRepoFeature::from_str(feature_str);
+            let feature = features::construct(feature_str)?;
             feature_vec.push(feature);
         }
 
-        let (gist_file, code) = next_code_elem(code, MissionCodeParseError::GistFileMissing)?;
-        let (gist_url, code) = next_code_elem(code, MissionCodeParseError::GistURLMissing)?;
-        let (gits_remote, code) = next_code_elem(code, MissionCodeParseError::GistRemoteMissing)?;
+        let (gist_file, code) = match codeless_info {
+            CodelessInfo::V0 | CodelessInfo::V1 => codeless_info.next_elem(code, MissionCodeParseError::GistFileMissing)?,
+            CodelessInfo::V2 => ("", code)
+        };
+        let (gist_url, code) = codeless_info.next_elem(code, MissionCodeParseError::GistURLMissing)?;
+        let (gits_remote, code) = codeless_info.next_elem(code, MissionCodeParseError::GistRemoteMissing)?;
 
         let gist_url = match gist_url {
             "None" => None,
@@ -241,18 +370,207 @@ impl MissionCode {
             return Err(MissionCodeParseError::HasNoGistRemoteOrURL)
         }
 
-        let content = code;
+        let mut files = match codeless_info {
+            CodelessInfo::V0 | CodelessInfo::V1 => {
+                let content = codeless_info.final_elem(code, MissionCodeParseError::CodeDataMissing)?;
+                vec![MissionFile { name: gist_file.to_string(), data: content.to_string(), payload: String::new() }]
+            },
+            CodelessInfo::V2 => {
+                let (file_count_str, code) = codeless_info.next_elem(code, MissionCodeParseError::FileCountMissing)?;
+                let file_count = match file_count_str.parse::<usize>() {
+                    Ok(c) if c > 0 => c,
+                    _ => return Err(MissionCodeParseError::FileCountInvalid)
+                };
+
+                let mut code = code;
+                let mut files = Vec::<MissionFile>::new();
+                for i in 0..file_count {
+                    let (file_name, code_slice) = codeless_info.next_elem(code, MissionCodeParseError::GistFileMissing)?;
+                    let (content, code_slice) = match i == file_count-1 {
+                        true => (codeless_info.final_elem(code_slice, MissionCodeParseError::CodeDataMissing)?, ""),
+                        false => codeless_info.next_elem(code_slice, MissionCodeParseError::CodeDataMissing)?
+                    };
+                    code = code_slice;
+
+                    files.push(MissionFile { name: file_name.to_string(), data: content.to_string(), payload: String::new() });
+                }
+                files
+            }
+        };
+
+        verify_file_names(&files)?;
+        for feature in &feature_vec {
+            feature.verify_files(&files)?;
+        }
+
+        // Decode once up front, so undecodable mission data is rejected before anything touches the repo
+        let encoding = feature_vec.iter().find_map(|f| { f.payload_encoding() }).unwrap_or_default();
+        for file in files.iter_mut() {
+            file.payload = match encoding.decode(&file.data) {
+                Ok(d) => d,
+                Err(e) => return Err(MissionCodeParseError::PayloadDecodeFailed(e))
+            };
+        }
+
+        let main_file = files.remove(0);
+
+        let context = MissionContext { gist_file: &main_file.name, payload: &main_file.payload, version_tag: None };
+        for feature in feature_vec.iter_mut() {
+            feature.attach(&context);
+        }
 
         return Ok(MissionCode { 
             codeless_fmt_version: codeless_info,
             codeless_features: feature_vec,
-            gist_file: gist_file.to_string(),
+            gist_file: main_file.name,
             gist_url: gist_url,
             gist_remote: gist_remote,
-            code_data: content.to_string()
+            code_data: main_file.data,
+            code_payload: main_file.payload,
+            extra_files: files
         });
     }
 
+    /// Serializes the mission code back into the codeless string it would be parsed from
+    /// 
+    /// Codes round-trip through `MissionCode::parse_from` unchanged, with the exception of V0 codes whose
+    /// features, gist file, gist URL or gist remote contain a `|`, as V0 has no way of representing them
+    /// 
+    /// Extra files are only serialized from V2 onwards, as earlier versions have no way of representing them
+    pub fn to_code_string(&self) -> String {
+        let fmt = &self.codeless_fmt_version;
+        let mut code = String::with_capacity(CODELESS_CM_IDENTIFIER.len() + self.gist_file.len() + self.code_data.len() + 64);
+
+        code.push_str(CODELESS_CM_IDENTIFIER);
+        code.push_str(CODELESS_ELEM_DELIMIT);
+        code.push_str(&fmt.version().to_string());
+        code.push_str(CODELESS_ELEM_DELIMIT);
+
+        fmt.push_elem(&mut code, &self.codeless_features.len().to_string());
+        for feature in &self.codeless_features {
+            fmt.push_elem(&mut code, &feature.to_code_str());
+        }
+
+        match fmt {
+            CodelessInfo::V0 | CodelessInfo::V1 => {
+                fmt.push_elem(&mut code, &self.gist_file);
+                fmt.push_elem(&mut code, self.gist_url.as_deref().unwrap_or("None"));
+                fmt.push_elem(&mut code, self.gist_remote.as_deref().unwrap_or("None"));
+                fmt.push_final_elem(&mut code, &self.code_data);
+            },
+            CodelessInfo::V2 => {
+                fmt.push_elem(&mut code, self.gist_url.as_deref().unwrap_or("None"));
+                fmt.push_elem(&mut code, self.gist_remote.as_deref().unwrap_or("None"));
+
+                let files = self.files();
+                fmt.push_elem(&mut code, &files.len().to_string());
+                for (i, (name, data)) in files.iter().enumerate() {
+                    fmt.push_elem(&mut code, name);
+                    match i == files.len()-1 {
+                        true => fmt.push_final_elem(&mut code, data),
+                        false => fmt.push_elem(&mut code, data)
+                    };
+                }
+            }
+        };
+
+        return code;
+    }
+
+    /// Upgrades the mission code to the newest codeless format version, returning the version it was upgraded from
+    /// Returns None if the mission code is already of the newest version
+    pub fn upgrade(&mut self) -> Option<usize> {
+        let version = self.codeless_fmt_version.version();
+        if version >= CodelessInfo::NEWEST.version() { return None; }
+
+        self.codeless_fmt_version = CodelessInfo::NEWEST;
+        return Some(version);
+    }
+
+    /// Every file published by the mission code as (name, undecoded data) pairs, beginning with gist_file
+    pub fn files(&self) -> Vec<(&str, &str)> {
+        let mut files = Vec::<(&str, &str)>::with_capacity(self.extra_files.len()+1);
+        files.push((&self.gist_file, &self.code_data));
+        for file in &self.extra_files {
+            files.push((&file.name, &file.data));
+        }
+
+        return files;
+    }
+
+    /// Every file published by the mission code as (name, decoded data) pairs, beginning with gist_file
+    pub fn payloads(&self) -> Vec<(&str, &str)> {
+        let mut payloads = Vec::<(&str, &str)>::with_capacity(self.extra_files.len()+1);
+        payloads.push((&self.gist_file, &self.code_payload));
+        for file in &self.extra_files {
+            payloads.push((&file.name, &file.payload));
+        }
+
+        return payloads;
+    }
+
+    /// The encoding of the data of every file, as declared by the Encoding feature
+    pub fn payload_encoding(&self) -> PayloadEncoding {
+        return self.codeless_features.iter().find_map(|f| { f.payload_encoding() }).unwrap_or_default();
+    }
+
+    /// Displays the mission version as tracked by its features, if any are present
+    /// 
+    /// Only meaningful once the features have been derived from & processed against a repo
+    pub fn version_display(&self) -> Option<String> {
+        let mut counter = None;
+        let mut semver = None;
+        for f in &self.codeless_features {
+            match f.version() {
+                Some(FeatureVersion::Revision(version)) => counter = Some(version),
+                Some(FeatureVersion::Semantic(version)) => semver = Some(version),
+                None => ()
+            };
+        }
+
+        return match (semver, counter) {
+            (Some(s), Some(c)) => Some(format!("v{} (Revision {})", s, c)),
+            (Some(s), None) => Some(format!("v{}", s)),
+            (None, Some(c)) => Some(format!("v{}", c)),
+            (None, None) => None
+        };
+    }
+
+    /// Author of the publish commit as (name, email), as declared by the Author feature
+    pub fn commit_author(&self) -> (Option<String>, Option<String>) {
+        return match self.codeless_features.iter().find_map(|f| { f.commit_author() }) {
+            Some(author) => (Some(author.name.clone()), author.email.clone()),
+            None => (None, None)
+        };
+    }
+
+    /// Returns a `MissionCodeParseError::FeatureUnknown` for every feature not understood by this program
+    pub fn unknown_features(&self) -> Vec<MissionCodeParseError> {
+        return self.codeless_features.iter().filter(|f| { f.is_unknown() }).map(|f| {
+            MissionCodeParseError::FeatureUnknown {
+                feature: f.name().to_string(),
+                suggestion: features::suggest_registered(f.name())
+            }
+        }).collect();
+    }
+
+    /// Logs every unknown feature, as an error if strict and otherwise as a warning
+    /// In strict mode the first unknown feature is returned as an error
+    pub fn report_unknown_features(&self, strict: bool, log: &cmterm::Log) -> Result<(), MissionCodeParseError> {
+        let mut unknown_features = self.unknown_features();
+        for unknown in &unknown_features {
+            match strict {
+                true => log.log_err(format!("Rejecting mission code as {}", unknown)),
+                false => log.log_warn(format!("Ignoring feature as {}", unknown))
+            };
+        }
+
+        return match strict && !unknown_features.is_empty() {
+            true => Err(unknown_features.remove(0)),
+            false => Ok(())
+        };
+    }
+
     pub fn feature_display(&self) -> String {
         let mut feature_strs = Vec::<String>::with_capacity(self.codeless_features.len());
 
@@ -278,13 +596,19 @@ impl MissionCode {
     }
 }
 
+impl Display for MissionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(&self.to_code_string());
+    }
+}
+
 impl RepoItem for MissionCode {
     fn derivable_children(&mut self) -> Option<Vec<&mut dyn RepoDerivable>> {
         let mut derivable = Vec::<&mut dyn RepoDerivable>::new();
         derivable.reserve_exact(self.codeless_features.len());
 
         for feature in self.codeless_features.iter_mut() {
-            derivable.push(feature);
+            derivable.push(feature.as_mut());
         }
 
         return Some(derivable);
@@ -296,16 +620,41 @@ impl RepoItem for MissionCode {
 
         publishable.push(&self.codeless_fmt_version);
         for feature in self.codeless_features.iter() {
-            publishable.push(feature);
+            publishable.push(feature.as_ref());
         }
 
         return Some(publishable);
     }
 }
 
+impl RepoDerivable for MissionCode {
+    fn repo_derive(&mut self, _files: &RepoFiles) -> Result<(), RepoError> { return Ok(()); }
+
+    /// Processed once every feature has been processed, so the final mission version is known
+    fn repo_process(&mut self, _files: &RepoFiles) -> Result<(), RepoError> {
+        let version_tag = self.version_display();
+        let context = MissionContext { gist_file: &self.gist_file, payload: &self.code_payload, version_tag: version_tag.as_deref() };
+        for feature in self.codeless_features.iter_mut() {
+            feature.attach(&context);
+        }
+
+        return Ok(());
+    }
+}
+
 impl RepoPublishable for MissionCode {
-    fn publish_target_file(&self) -> String {
-        self.gist_file.clone()
+    /// Files larger than MAX_CODE_SIZE are published as an index file followed by numbered part files
+    fn publish_targets(&self) -> Result<Vec<PublishTarget>, RepoError> {
+        let mut targets = Vec::<PublishTarget>::with_capacity(self.extra_files.len()+1);
+        for (name, payload) in self.payloads() {
+            targets.append(&mut split_publish_target(PublishTarget { file: name.to_string(), data: payload.to_string() }).0);
+        }
+
+        return Ok(targets);
+    }
+
+    fn publish_extras(&self) -> Vec<String> {
+        return self.codeless_features.iter().filter(|f| { f.publishes_content() }).map(|f| { f.to_string() }).collect();
     }
 
     fn publish_target_remote(&self, repo: &Repository) -> Result<String, RepoError> {
@@ -325,37 +674,46 @@ impl RepoPublishable for MissionCode {
         return Err(RepoError::PublishError(String::from("MissionCode is invalid - has both remote name & URL")));
     }
 
+    /// Notes declared by features are appended as the body of the message
     fn publish_message(&self) -> String {
-        let mission_version = self.codeless_features.iter().find_map(|f| {
-            match f {
-                CodelessRepoFeature::MissionVersion(v) => Some(*v),
-                _ => None
-            }
-        });
-
-        return match mission_version {
-            Some(v) => format!("Update To Newest Version - v{}", v),
+        let mut message = match self.version_display() {
+            Some(v) => format!("Update To Newest Version - {}", v),
             None => String::from("Update To Newest Version - Untracked")
         };
+
+        for notes in self.codeless_features.iter().filter_map(|f| { f.commit_notes() }) {
+            message.push_str("\n\n");
+            message.push_str(notes.trim());
+        }
+
+        return message;
     }
 
-    fn repo_publish(&self, repo: &Repository) -> Result<(), RepoError> {
-        repo_management::overwrite_file(repo, &self.gist_file, &self.code_data)?;
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError> {
+        for (name, payload) in self.payloads() {
+            let (targets, part_count) = split_publish_target(PublishTarget { file: name.to_string(), data: payload.to_string() });
+            for target in &targets {
+                files.overwrite_file(&target.file, &target.data)?;
+            }
+            remove_stale_parts(files, name, part_count)?;
+        }
+
         return Ok(())
     }
 
-    fn repo_valid(&self, repo: &Repository) -> Result<(), RepoError> {
-        // Valid if gist file exists && remote matches
-        let p = repo_management::get_repo_file_path(repo, &self.gist_file)?;
-        match p.exists() && !p.is_file() {
-            true => return Err(RepoError::PublishError(format!("non-file item already exists at {}", self.gist_file))),
-            false => ()
-        };
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError> {
+        // Valid if gist files exist && remote matches
+        for (name, _) in self.files() {
+            match files.non_file_exists(name)? {
+                true => return Err(RepoError::PublishError(format!("non-file item already exists at {}", name))),
+                false => ()
+            };
+        }
 
         let repo_is_valid = match self.gist_url.as_ref() {
-            Some(url) => repo_management::has_remote_url(repo, url)?,
+            Some(url) => repo_management::has_remote_url(files.repo(), url)?,
             None => match self.gist_remote.as_ref() {
-                Some(remote) => repo_management::has_remote(repo, remote)?,
+                Some(remote) => repo_management::has_remote(files.repo(), remote)?,
                 None => return Err(RepoError::PublishError(String::from("mission code is missing both remote name and URL")))
             }
         };
@@ -368,4 +726,320 @@ impl RepoPublishable for MissionCode {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::{metadata::MissionMetadata, semver::{SemVerBump, SemanticVersion}};
+
+    fn parse(code: &str) -> Result<MissionCode, MissionCodeParseError> {
+        features::register_builtin();
+        return MissionCode::parse_from(code);
+    }
+
+    fn assert_round_trip(code: &str) {
+        let parsed = match parse(code) {
+            Ok(c) => c,
+            Err(e) => panic!("failed to parse \"{code}\" with error {e}")
+        };
+
+        assert_eq!(parsed.to_code_string(), code);
+    }
+
+    #[test]
+    fn v0_round_trip() {
+        assert_round_trip("_infilengine_cm_codeless_|0|1|MissionVersion|mission.txt|None|origin|some mission data");
+        assert_round_trip("_infilengine_cm_codeless_|0|0|mission.txt|https://gist.github.com/abc|None|");
+    }
+
+    #[test]
+    fn v0_round_trip_data_with_delimiter() {
+        assert_round_trip("_infilengine_cm_codeless_|0|2|MissionVersion|Whatever|mission.txt|None|origin|data|with|pipes");
+    }
+
+    #[test]
+    fn v1_round_trip() {
+        assert_round_trip("_infilengine_cm_codeless_|1|1:1|14:MissionVersion|11:mission.txt|4:None|6:origin|17:some mission data");
+        assert_round_trip("_infilengine_cm_codeless_|1|1:0|11:mission.txt|27:https://gist.github.com/abc|4:None|0:");
+    }
+
+    #[test]
+    fn v1_round_trip_fields_with_delimiter() {
+        let code = MissionCode {
+            codeless_fmt_version: CodelessInfo::V1,
+            codeless_features: vec![features::construct("Some|Feature=arg").unwrap()],
+            gist_file: String::from("mission|file.txt"),
+            gist_url: None,
+            gist_remote: Some(String::from("my|remote")),
+            code_data: String::from("data|with:delimiters|"),
+            code_payload: String::from("data|with:delimiters|"),
+            extra_files: vec![]
+        };
+
+        let code_string = code.to_code_string();
+        let parsed = parse(&code_string).expect("serialized code should parse");
+
+        assert_eq!(parsed.gist_file, code.gist_file);
+        assert_eq!(parsed.gist_remote, code.gist_remote);
+        assert_eq!(parsed.code_data, code.code_data);
+        assert_eq!(parsed.feature_display(), code.feature_display());
+        assert_eq!(parsed.to_code_string(), code_string);
+    }
+
+    #[test]
+    fn feature_arguments() {
+        assert_round_trip("_infilengine_cm_codeless_|0|2|MissionVersion=12|Unknown=some arg|mission.txt|None|origin|data");
+        assert_round_trip("_infilengine_cm_codeless_|0|1|MissionVersion=012|mission.txt|None|origin|data");
+
+        let code = parse("_infilengine_cm_codeless_|0|1|MissionVersion=12|mission.txt|None|origin|data").unwrap();
+        assert_eq!(code.codeless_features[0].name(), "MissionVersion");
+        assert_eq!(code.codeless_features[0].argument().as_deref(), Some("12"));
+
+        let result = parse("_infilengine_cm_codeless_|0|1|MissionVersion=twelve|mission.txt|None|origin|data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentInvalid { .. })));
+    }
+
+    #[test]
+    fn unknown_feature_suggestions() {
+        let code = parse("_infilengine_cm_codeless_|0|3|MisionVersion|Unrelated|MissionVersion|mission.txt|None|origin|data").unwrap();
+        let unknown = code.unknown_features();
+
+        assert_eq!(unknown.len(), 2);
+        assert!(matches!(&unknown[0], MissionCodeParseError::FeatureUnknown { feature, suggestion: Some(s) } if feature == "MisionVersion" && s == "MissionVersion"));
+        assert!(matches!(&unknown[1], MissionCodeParseError::FeatureUnknown { feature, suggestion: None } if feature == "Unrelated"));
+    }
+
+    #[test]
+    fn semver_bumps() {
+        assert_round_trip("_infilengine_cm_codeless_|0|1|SemVer=minor|mission.txt|None|origin|data");
+        assert_round_trip("_infilengine_cm_codeless_|0|1|SemVer|mission.txt|None|origin|data");
+        assert_round_trip("_infilengine_cm_codeless_|0|1|SemVer=MAJOR|mission.txt|None|origin|data");
+
+        let mut version = "1.2.3".parse::<SemanticVersion>().unwrap();
+        version.bump(SemVerBump::Patch);
+        assert_eq!(version.to_string(), "1.2.4");
+        version.bump(SemVerBump::Minor);
+        assert_eq!(version.to_string(), "1.3.0");
+        version.bump(SemVerBump::Major);
+        assert_eq!(version.to_string(), "2.0.0");
+
+        let result = parse("_infilengine_cm_codeless_|0|1|SemVer=huge|mission.txt|None|origin|data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentInvalid { .. })));
+    }
+
+    #[test]
+    fn changelog_requires_notes() {
+        assert_round_trip("_infilengine_cm_codeless_|1|1:2|14:MissionVersion|26:Changelog=Fixed|vent spawn|11:mission.txt|4:None|6:origin|4:data");
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Changelog|mission.txt|None|origin|data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentMissing(f)) if f == "Changelog"));
+    }
+
+    #[test]
+    fn metadata_arguments() {
+        assert_round_trip("_infilengine_cm_codeless_|0|1|Metadata=name:Heist;author:Sprix;description:Rob\\; then escape\\:;engine:1.2|mission.txt|None|origin|data");
+
+        assert_round_trip("_infilengine_cm_codeless_|0|1|Metadata=engine:1;name:x|mission.txt|None|origin|data");
+
+        let code = parse("_infilengine_cm_codeless_|0|1|Metadata=description:a\\;b;name:Heist|mission.txt|None|origin|data").unwrap();
+        assert_eq!(code.codeless_features[0].argument().as_deref(), Some("description:a\\;b;name:Heist"));
+        assert_eq!(code.feature_display(), "Metadata[Heist]");
+
+        let metadata = "description:a\\;b;name:Heist".parse::<MissionMetadata>().unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Heist"));
+        assert_eq!(metadata.description.as_deref(), Some("a;b"));
+        assert_eq!(metadata.author, None);
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Metadata=colour:red|mission.txt|None|origin|data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentInvalid { .. })));
+    }
+
+    #[test]
+    fn encoded_payloads() {
+        let mission_data = "mission data|with delimiters ".repeat(32);
+
+        for encoding in [PayloadEncoding::Plain, PayloadEncoding::Base64, PayloadEncoding::Base64Deflate] {
+            let code = format!("_infilengine_cm_codeless_|0|1|Encoding={}|mission.txt|None|origin|{}", encoding, encoding.encode(&mission_data));
+            assert_round_trip(&code);
+
+            let parsed = parse(&code).unwrap();
+            assert_eq!(parsed.payload_encoding(), encoding);
+            assert_eq!(parsed.publish_targets().unwrap()[0].data, mission_data);
+        }
+
+        assert_round_trip("_infilengine_cm_codeless_|0|1|Encoding=BASE64|mission.txt|None|origin|aGVsbG8=");
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Encoding=base64|mission.txt|None|origin|not base64!");
+        assert!(matches!(result, Err(MissionCodeParseError::PayloadDecodeFailed(PayloadDecodeError::Base64(_)))));
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Encoding=base64+deflate|mission.txt|None|origin|aGVsbG8=");
+        assert!(matches!(result, Err(MissionCodeParseError::PayloadDecodeFailed(PayloadDecodeError::Inflate(_)))));
+
+        let oversized = "0".repeat(crate::payload::MAX_INFLATED_SIZE as usize + 1);
+        let result = parse(&format!("_infilengine_cm_codeless_|0|1|Encoding=base64+deflate|mission.txt|None|origin|{}", PayloadEncoding::Base64Deflate.encode(&oversized)));
+        assert!(matches!(result, Err(MissionCodeParseError::PayloadDecodeFailed(PayloadDecodeError::TooLarge(_)))));
+    }
+
+    #[test]
+    fn v2_multiple_files() {
+        let code = "_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:3|11:mission.txt|4:main|9:props.txt|12:prop|pack|me|10:config.txt|6:config";
+        assert_round_trip(code);
+
+        let parsed = parse(code).unwrap();
+        assert_eq!(parsed.gist_file, "mission.txt");
+        assert_eq!(parsed.code_data, "main");
+        assert_eq!(parsed.files(), vec![("mission.txt", "main"), ("props.txt", "prop|pack|me"), ("config.txt", "config")]);
+
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:0|");
+        assert!(matches!(result, Err(MissionCodeParseError::FileCountInvalid)));
+
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:2|11:mission.txt|4:main");
+        assert!(matches!(result, Err(MissionCodeParseError::GistFileMissing)));
+    }
+
+    #[test]
+    fn file_names_within_repo() {
+        for name in ["../escape.txt", "/etc/passwd", "./mission.txt", "dir/../../escape.txt", ""] {
+            let result = parse(&format!("_infilengine_cm_codeless_|1|1:0|{}:{}|4:None|6:origin|4:data", name.len(), name));
+            assert!(matches!(result, Err(MissionCodeParseError::FileNameInvalid(n)) if n == name), "{}", name);
+        }
+
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:2|11:mission.txt|4:main|14:../x.txt.part1|4:part");
+        assert!(matches!(result, Err(MissionCodeParseError::FileNameInvalid(_))));
+
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:2|11:mission.txt|4:main|11:mission.txt|4:copy");
+        assert!(matches!(result, Err(MissionCodeParseError::FileNameDuplicate(n)) if n == "mission.txt"));
+
+        assert!(parse("_infilengine_cm_codeless_|1|1:0|17:missions/vent.txt|4:None|6:origin|4:data").is_ok());
+    }
+
+    #[test]
+    fn huge_counts_rejected() {
+        // Counts are untrusted, so must not be allocated for up front
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|11:99999999999|11:mission.txt|4:main");
+        assert!(matches!(result, Err(MissionCodeParseError::GistFileMissing)));
+
+        let result = parse("_infilengine_cm_codeless_|1|11:99999999999|14:MissionVersion|11:mission.txt|4:None|6:origin|4:data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureMissing)));
+    }
+
+    #[test]
+    fn oversized_files_split() {
+        let mission_data = "é".repeat(MAX_CODE_SIZE);
+        let code = parse(&format!("_infilengine_cm_codeless_|0|0|mission.txt|None|origin|{}", mission_data)).unwrap();
+        let targets = code.publish_targets().unwrap();
+
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].file, "mission.txt.index");
+        assert_eq!(targets[0].data, "mission.txt.part1\nmission.txt.part2\n");
+        assert_eq!(targets[1].file, "mission.txt.part1");
+        assert_eq!(targets[1].data.len(), MAX_CODE_SIZE);
+        assert_eq!(format!("{}{}", targets[1].data, targets[2].data), mission_data);
+    }
+
+    #[test]
+    fn split_files_replace_unsplit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+        let workdir_file = |file: &str| { dir.path().join(file) };
+
+        let small = parse("_infilengine_cm_codeless_|0|0|mission.txt|None|origin|small").unwrap();
+        let large = parse(&format!("_infilengine_cm_codeless_|0|0|mission.txt|None|origin|{}", "a".repeat(MAX_CODE_SIZE+1))).unwrap();
+
+        small.repo_publish(&mut files).unwrap();
+        assert!(workdir_file("mission.txt").is_file());
+
+        large.repo_publish(&mut files).unwrap();
+        assert!(!workdir_file("mission.txt").exists());
+        assert!(workdir_file("mission.txt.index").is_file());
+        assert!(workdir_file("mission.txt.part2").is_file());
+
+        small.repo_publish(&mut files).unwrap();
+        assert!(workdir_file("mission.txt").is_file());
+        assert!(!workdir_file("mission.txt.index").exists());
+        assert!(!workdir_file("mission.txt.part1").exists());
+        assert!(!workdir_file("mission.txt.part2").exists());
+    }
+
+    #[test]
+    fn part_like_names_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut files = RepoFiles::new(&repo);
+
+        // Named like a part of mission.txt, but is a file of its own
+        let code = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:2|11:mission.txt|4:main|24:mission.txt.partial.json|2:{}").unwrap();
+        code.repo_publish(&mut files).unwrap();
+        assert_eq!(files.read_file("mission.txt").unwrap(), "main");
+        assert_eq!(files.read_file("mission.txt.partial.json").unwrap(), "{}");
+    }
+
+    #[test]
+    fn author_and_notes() {
+        assert_round_trip("_infilengine_cm_codeless_|1|1:2|32:Author=Sprix <sprix@example.com>|20:Notes=Fixed the vent|11:mission.txt|4:None|6:origin|4:data");
+
+        let code = parse("_infilengine_cm_codeless_|0|2|Author=Sprix <sprix@example.com>|Notes=Fixed the vent|mission.txt|None|origin|data").unwrap();
+        assert_eq!(code.commit_author(), (Some(String::from("Sprix")), Some(String::from("sprix@example.com"))));
+        assert_eq!(code.publish_message(), "Update To Newest Version - Untracked\n\nFixed the vent");
+
+        assert_round_trip("_infilengine_cm_codeless_|0|1|Author=Sprix<sprix@example.com>|mission.txt|None|origin|data");
+
+        let code = parse("_infilengine_cm_codeless_|0|1|Author=Sprix|mission.txt|None|origin|data").unwrap();
+        assert_eq!(code.commit_author(), (Some(String::from("Sprix")), None));
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Author=<sprix@example.com>|mission.txt|None|origin|data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentInvalid { .. })));
+    }
+
+    #[test]
+    fn checksum_verification() {
+        let files = [MissionFile { name: String::from("mission.txt"), data: String::from("some mission data"), payload: String::new() }];
+        let checksum = features::checksum::PayloadChecksum::of_files(&files);
+        assert_eq!(checksum.length, 17);
+
+        let code = format!("_infilengine_cm_codeless_|0|1|Checksum={}|mission.txt|None|origin|some mission data", checksum);
+        assert_round_trip(&code);
+        assert_round_trip(&format!("_infilengine_cm_codeless_|0|1|Checksum={}|mission.txt|None|origin|some mission data", checksum.to_string().to_uppercase()));
+
+        let result = parse(&format!("_infilengine_cm_codeless_|0|1|Checksum={}|mission.txt|None|origin|some mission", checksum));
+        assert!(matches!(result, Err(MissionCodeParseError::ChecksumMismatch { .. })));
+
+        let result = parse("_infilengine_cm_codeless_|0|1|Checksum=17:xyz|mission.txt|None|origin|some mission data");
+        assert!(matches!(result, Err(MissionCodeParseError::FeatureArgumentInvalid { .. })));
+    }
+
+    #[test]
+    fn upgrade_to_newest() {
+        let mut code = parse("_infilengine_cm_codeless_|0|1|MissionVersion=3|mission.txt|None|origin|data|with|pipes").unwrap();
+        assert_eq!(code.upgrade(), Some(0));
+        assert_eq!(code.upgrade(), None);
+
+        let upgraded = code.to_code_string();
+        assert_eq!(upgraded, "_infilengine_cm_codeless_|2|1:1|16:MissionVersion=3|4:None|6:origin|1:1|11:mission.txt|15:data|with|pipes");
+        assert_round_trip(&upgraded);
+    }
+
+    #[test]
+    fn v1_rejects_trailing_delimiter() {
+        let result = parse("_infilengine_cm_codeless_|1|1:0|11:mission.txt|4:None|6:origin|0:|");
+        assert!(matches!(result, Err(MissionCodeParseError::TrailingData)));
+
+        let result = parse("_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:1|11:mission.txt|4:data|");
+        assert!(matches!(result, Err(MissionCodeParseError::TrailingData)));
+    }
+
+    #[test]
+    fn identifier_without_delimiter_rejected() {
+        for code in ["_infilengine_cm_codeless_", "_infilengine_cm_codeless_é|1", "_infilengine_cm_codeless", ""] {
+            assert!(matches!(parse(code), Err(MissionCodeParseError::InputWasntCode)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn v1_rejects_truncated_data() {
+        let result = parse("_infilengine_cm_codeless_|1|1:0|11:mission.txt|4:None|6:origin|17:some mission");
+        assert!(matches!(result, Err(MissionCodeParseError::ElementTruncated)));
+    }
+}
@@ -0,0 +1,108 @@
+use std::{error::Error, fmt::Display, io::{self, Read}, string::FromUtf8Error};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::read::DeflateDecoder;
+
+/// Largest size deflated mission data may inflate to, so small codes can't expand into huge files
+pub const MAX_INFLATED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Encoding applied to mission data within a mission code, allowing larger missions to fit within a single code
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PayloadEncoding {
+    #[default]
+    Plain,
+    Base64,
+
+    /// Raw DEFLATE compressed, then base64 encoded
+    Base64Deflate
+}
+
+#[derive(Debug)]
+pub enum PayloadDecodeError {
+    Base64(base64::DecodeError),
+    Inflate(io::Error),
+    TooLarge(u64),
+    NotUtf8(FromUtf8Error)
+}
+
+impl Display for PayloadDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Base64(e) => f.write_fmt(format_args!("failed to base64 decode mission data with error {e}")),
+            Self::Inflate(e) => f.write_fmt(format_args!("failed to decompress deflated mission data with error {e}")),
+            Self::TooLarge(limit) => f.write_fmt(format_args!("deflated mission data inflates to more than {limit} bytes")),
+            Self::NotUtf8(e) => f.write_fmt(format_args!("decoded mission data was not valid UTF-8 with error {e}"))
+        }
+    }
+}
+
+impl Error for PayloadDecodeError {}
+
+impl PayloadEncoding {
+    pub fn decode(&self, data: &str) -> Result<String, PayloadDecodeError> {
+        let decoded = match self {
+            PayloadEncoding::Plain => return Ok(data.to_string()),
+            PayloadEncoding::Base64 | PayloadEncoding::Base64Deflate => match BASE64.decode(data.trim()) {
+                Ok(d) => d,
+                Err(e) => return Err(PayloadDecodeError::Base64(e))
+            }
+        };
+
+        let decoded = match self {
+            PayloadEncoding::Base64Deflate => {
+                let mut inflated = Vec::<u8>::with_capacity(decoded.len() * 4);
+                match DeflateDecoder::new(decoded.as_slice()).take(MAX_INFLATED_SIZE+1).read_to_end(&mut inflated) {
+                    Ok(size) if size as u64 > MAX_INFLATED_SIZE => return Err(PayloadDecodeError::TooLarge(MAX_INFLATED_SIZE)),
+                    Ok(_) => inflated,
+                    Err(e) => return Err(PayloadDecodeError::Inflate(e))
+                }
+            },
+            _ => decoded
+        };
+
+        return match String::from_utf8(decoded) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(PayloadDecodeError::NotUtf8(e))
+        };
+    }
+
+    /// Counterpart to decode, used to produce encoded mission codes in tests
+    #[cfg(test)]
+    pub fn encode(&self, data: &str) -> String {
+        use std::io::Write;
+        use flate2::{write::DeflateEncoder, Compression};
+
+        return match self {
+            PayloadEncoding::Plain => data.to_string(),
+            PayloadEncoding::Base64 => BASE64.encode(data),
+            PayloadEncoding::Base64Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::<u8>::with_capacity(data.len()), Compression::best());
+                encoder.write_all(data.as_bytes()).expect("writing to an in-memory deflate encoder shouldn't fail");
+                BASE64.encode(encoder.finish().expect("finishing an in-memory deflate encoder shouldn't fail"))
+            }
+        };
+    }
+}
+
+impl std::str::FromStr for PayloadEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.to_lowercase().as_str() {
+            "plain" => Ok(PayloadEncoding::Plain),
+            "base64" => Ok(PayloadEncoding::Base64),
+            "base64+deflate" => Ok(PayloadEncoding::Base64Deflate),
+            _ => Err(String::from("expected one of plain, base64 or base64+deflate"))
+        };
+    }
+}
+
+impl Display for PayloadEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(match self {
+            PayloadEncoding::Plain => "plain",
+            PayloadEncoding::Base64 => "base64",
+            PayloadEncoding::Base64Deflate => "base64+deflate"
+        });
+    }
+}
@@ -1,5 +1,5 @@
 use std::{path::PathBuf, sync::{Arc, OnceLock}};
-use clap::{arg, command, Parser};
+use clap::{arg, command, Parser, Subcommand};
 
 use crate::cmterm;
 
@@ -38,6 +38,14 @@ pub struct ProgramArgs {
     #[arg(short='c', long="hide-url", default_value_t=false)]
     pub hide_url: bool,
 
+    /// Reject mission codes containing features unknown to this program, rather than warning and ignoring them
+    #[arg(short='s', long="strict-features", default_value_t=false)]
+    pub strict_features: bool,
+
+    /// Build publish commits in memory rather than writing to the working directory, always enabled for bare repos
+    #[arg(short='m', long="in-memory", default_value_t=false)]
+    pub in_memory: bool,
+
     /// [Experimental] Use the program to clone a gist repo without the need of interfacing with a git client
     #[arg(short='d', long="download-repo", default_value_t=false)]
     pub download_repo: bool,
@@ -54,6 +62,46 @@ pub struct ProgramArgs {
     #[arg(long="redraw-delay", value_name="MILLISECONDS", default_value_t=250)]
     pub terminal_redraw_delay: u64,
 
+    #[command(subcommand)]
+    pub command: Option<ProgramCommand>,
+}
+
+/// Commands which run once without starting the interactive terminal interface or internal http server
+#[derive(Subcommand, Debug)]
+pub enum ProgramCommand {
+    /// Prepares the repo for publishing by creating the state files of every known feature
+    Init,
+
+    /// Prints a breakdown of a mission code without publishing it
+    Inspect {
+        /// File containing the mission code, reads from stdin if omitted or "-"
+        #[arg(value_name="FILE", value_hint=clap::ValueHint::FilePath)]
+        code_path: Option<PathBuf>
+    },
+
+    /// Upgrades stored mission codes to the newest codeless version, and migrates the repo state of their features
+    /// Exits with 1 if a mission code could not be read, parsed or migrated
+    Migrate {
+        /// Reports what would change without writing anything
+        #[arg(long="dry-run", default_value_t=false)]
+        dry_run: bool,
+
+        /// Files containing the mission codes, each rewritten in place
+        #[arg(value_name="FILE", value_hint=clap::ValueHint::FilePath, required=true)]
+        code_paths: Vec<PathBuf>
+    },
+
+    /// Publishes a mission code to the repo, printing the raw URL of every published file
+    /// Exits with 1 if the mission code could not be read or parsed, or 2 if publishing failed
+    Publish {
+        /// Also copies the URL of the first published file to the clipboard
+        #[arg(long="copy", default_value_t=false)]
+        copy: bool,
+
+        /// File containing the mission code, reads from stdin if omitted or "-"
+        #[arg(value_name="FILE", value_hint=clap::ValueHint::FilePath)]
+        code_path: Option<PathBuf>
+    },
 }
 
 pub struct ProgramInfo {
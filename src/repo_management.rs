@@ -1,6 +1,6 @@
-use std::{error::Error, fmt::{Debug, Display}, fs, io::{self, Read, Seek, Write}, path::{Path, PathBuf}};
+use std::{error::Error, fmt::{Debug, Display}, fs, io::{self, Read, Seek, Write}, path::{Component, Path, PathBuf}, str::FromStr};
 
-use git2::{Index, IndexAddOption, Remote, Repository, Signature};
+use git2::{build::CheckoutBuilder, Commit, Index, IndexEntry, IndexTime, Oid, Remote, Repository, Signature, Status, StatusOptions, Tree};
 
 use crate::cmterm::{self, LogHandle};
 
@@ -15,6 +15,7 @@ pub enum RepoError {
 
     PublishError(String),
     DeriveError(String),
+    CommandFailed{command: String, reason: String},
 
     HeadCheckFailed(String),
     HeadDetached(String),
@@ -39,6 +40,7 @@ impl Display for RepoError {
 
             Self::PublishError(s) => f.write_fmt(format_args!("publish error: {s}")),
             Self::DeriveError(s) => f.write_fmt(format_args!("derive error: {s}")),
+            Self::CommandFailed{command, reason} => f.write_fmt(format_args!("external command {command} failed with reason \"{reason}\"")),
 
             Self::CloneFailed(s) => f.write_fmt(format_args!("clone error: {s}"))
         }
@@ -58,16 +60,24 @@ pub trait RepoPublishable : RepoItem {
     #[allow(unused_variables)] // should only be unused in default implementation
     fn publish_target_remote(&self, repo: &Repository) -> Result<String, RepoError> { unimplemented!(); }
 
-    #[allow(unused_variables)] // should only be unused in default implementation
-    fn publish_target_file(&self) -> String { unimplemented!(); }
+    /// Every file published by the item, the first of which is copied to the clipboard once published
+    fn publish_targets(&self) -> Result<Vec<PublishTarget>, RepoError> { unimplemented!(); }
+
+    /// Everything published alongside the publish targets, which is skipped along with them when they are unchanged
+    fn publish_extras(&self) -> Vec<String> { return Vec::new(); }
 
-    fn repo_publish(&self, repo: &Repository) -> Result<(), RepoError>;
-    fn repo_valid(&self, repo: &Repository) -> Result<(), RepoError>;
+    fn repo_publish(&self, files: &mut RepoFiles) -> Result<(), RepoError>;
+    fn repo_valid(&self, files: &RepoFiles) -> Result<(), RepoError>;
 }
 
 pub trait RepoDerivable : RepoItem {
-    fn repo_derive(&mut self, repo: &Repository) -> Result<(), RepoError>;
-    fn repo_process(&mut self, repo: &Repository) -> Result<(), RepoError>;
+    /// Seeds default state into the repo if the state this item derives from is missing
+    /// Returns a description of what was created, or None if the repo was already initialized
+    #[allow(unused_variables)] // should only be unused in default implementation
+    fn repo_init(&mut self, files: &mut RepoFiles) -> Result<Option<String>, RepoError> { return Ok(None); }
+
+    fn repo_derive(&mut self, files: &RepoFiles) -> Result<(), RepoError>;
+    fn repo_process(&mut self, files: &RepoFiles) -> Result<(), RepoError>;
 }
 
 pub fn get_repo(path: impl AsRef<Path>) -> Result<Repository, RepoError> {
@@ -215,7 +225,241 @@ pub fn get_repo_file_path(repo: &Repository, file: &str) -> Result<PathBuf, Repo
     return Ok(target_path);
 }
 
-pub fn overwrite_file(repo: &Repository, file: &str, contents: &str) -> Result<(), RepoError> {
+/// The state of a file before it was first written through a RepoFiles
+enum JournalEntry {
+    /// Written through RepoFiles, with the contents the file held beforehand, or None if it didn't exist
+    Written{file: String, prior: Option<Vec<u8>>},
+
+    /// Written by an external command, so its prior contents are unknown and are restored from the parent commit instead
+    External{file: String}
+}
+
+impl JournalEntry {
+    fn file(&self) -> &str {
+        return match self {
+            JournalEntry::Written { file, .. } => file,
+            JournalEntry::External { file } => file
+        };
+    }
+}
+
+/// Where a RepoFiles reads & writes files
+enum FileStorage {
+    Workdir,
+
+    /// A tree built in memory from a commit, read from & written to in place of the workdir
+    InMemory(Index)
+}
+
+/// Reads & writes the files of a repo on behalf of its items, recording every file written
+/// so a publish knows exactly what to commit, and what to restore if it fails
+pub struct RepoFiles<'r> {
+    repo: &'r Repository,
+    storage: FileStorage,
+    journal: Vec<JournalEntry>
+}
+
+impl<'r> RepoFiles<'r> {
+    /// Files of the repo's workdir
+    pub fn new(repo: &'r Repository) -> Self {
+        return RepoFiles { repo: repo, storage: FileStorage::Workdir, journal: Vec::new() };
+    }
+
+    /// Files of an in-memory copy of the tree, leaving the workdir untouched
+    pub fn in_memory(repo: &'r Repository, tree: &Tree) -> Result<Self, RepoError> {
+        let mut index = match Index::new() {
+            Ok(i) => i,
+            Err(e) => return Err(RepoError::GitErr(e, String::from("create in-memory tree")))
+        };
+
+        match index.read_tree(tree) {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::GitErr(e, String::from("read tree into in-memory tree")))
+        };
+
+        return Ok(RepoFiles { repo: repo, storage: FileStorage::InMemory(index), journal: Vec::new() });
+    }
+
+    pub fn repo(&self) -> &'r Repository {
+        return self.repo;
+    }
+
+    /// The workdir files are written to, or None if they aren't written to a workdir
+    pub fn workdir(&self) -> Option<&'r Path> {
+        return match self.storage {
+            FileStorage::Workdir => self.repo.workdir(),
+            FileStorage::InMemory(_) => None
+        };
+    }
+
+    fn journaled(&self, file: &str) -> bool {
+        return self.journal.iter().any(|e| { e.file() == file });
+    }
+
+    /// Records the current contents of the file before its first write
+    fn record_write(&mut self, file: &str) -> Result<(), RepoError> {
+        if self.journaled(file) { return Ok(()); }
+
+        let prior = match self.workdir() {
+            Some(workdir) => match fs::read(workdir.join(file)) {
+                Ok(contents) => Some(contents),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(RepoError::FailRead { err: e, repo: repo_errname(self.repo), file: file.to_string() })
+            },
+            None => None
+        };
+
+        self.journal.push(JournalEntry::Written { file: file.to_string(), prior: prior });
+        return Ok(());
+    }
+
+    /// Records a file written by an external command
+    pub fn record_external(&mut self, file: &str) {
+        // Normalized to match the paths git reports, as commands may report paths such as ./file
+        let file = Path::new(file).components()
+            .filter(|c| { matches!(c, Component::Normal(_)) })
+            .map(|c| { c.as_os_str().to_string_lossy().into_owned() })
+            .collect::<Vec<String>>()
+            .join("/");
+
+        if !self.journaled(&file) {
+            self.journal.push(JournalEntry::External { file: file });
+        }
+    }
+
+    /// Repo relative path of every file written, in the order they were first written
+    pub fn written(&self) -> Vec<String> {
+        return self.journal.iter().map(|e| { e.file().to_string() }).collect();
+    }
+
+    /// Returns true if a file exists at the given path within the repo
+    pub fn file_exists(&self, file: &str) -> Result<bool, RepoError> {
+        return match &self.storage {
+            FileStorage::Workdir => workdir_file_exists(self.repo, file),
+            FileStorage::InMemory(tree) => Ok(tree.get_path(Path::new(file), 0).is_some())
+        };
+    }
+
+    /// Returns true if something other than a file, such as a directory, exists at the given path within the repo
+    pub fn non_file_exists(&self, file: &str) -> Result<bool, RepoError> {
+        return match &self.storage {
+            FileStorage::Workdir => workdir_non_file_exists(self.repo, file),
+            FileStorage::InMemory(tree) => Ok(in_memory_dir_exists(tree, file))
+        };
+    }
+
+    pub fn read_file(&self, file: &str) -> Result<String, RepoError> {
+        return match &self.storage {
+            FileStorage::Workdir => workdir_read_file(self.repo, file),
+            FileStorage::InMemory(tree) => in_memory_read(self.repo, tree, file)
+        };
+    }
+
+    pub fn overwrite_file(&mut self, file: &str, contents: &str) -> Result<(), RepoError> {
+        if self.non_file_exists(file)? {
+            return Err(RepoError::FileInvalid { repo: repo_errname(self.repo), file: file.to_string(), reason: String::from("non-file item exists at path") });
+        }
+
+        self.record_write(file)?;
+        return match &mut self.storage {
+            FileStorage::Workdir => workdir_overwrite_file(self.repo, file, contents),
+            FileStorage::InMemory(tree) => in_memory_write(self.repo, tree, file, contents.as_bytes())
+        };
+    }
+
+    /// Removes the file if it exists, returning whether a file was removed
+    pub fn remove_file(&mut self, file: &str) -> Result<bool, RepoError> {
+        if !self.file_exists(file)? { return Ok(false); }
+
+        self.record_write(file)?;
+        return match &mut self.storage {
+            FileStorage::Workdir => workdir_remove_file(self.repo, file),
+            FileStorage::InMemory(tree) => in_memory_remove(tree, file)
+        };
+    }
+}
+
+/// Git config entry making publishes to a repo with a workdir build their commit in memory
+const IN_MEMORY_CONFIG: &'static str = "codeless.inMemory";
+
+/// Mode of a regular, non-executable file
+const BLOB_FILE_MODE: u32 = 0o100644;
+
+/// Index entry of a blob, with no stat data as the blob isn't checked out
+fn blob_index_entry(path: &[u8], id: Oid, mode: u32, size: usize) -> IndexEntry {
+    return IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: mode,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id: id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.to_vec()
+    };
+}
+
+/// True if the in-memory tree holds a directory at the given path
+fn in_memory_dir_exists(tree: &Index, file: &str) -> bool {
+    return tree.get_path(Path::new(file), 0).is_none() && tree.find_prefix(format!("{}/", file).as_str()).is_ok();
+}
+
+fn in_memory_write(repo: &Repository, tree: &mut Index, file: &str, contents: &[u8]) -> Result<(), RepoError> {
+    if in_memory_dir_exists(tree, file) {
+        return Err(RepoError::FileInvalid { repo: repo_errname(repo), file: file.to_string(), reason: String::from("non-file item exists at path") });
+    }
+
+    let blob = match repo.blob(contents) {
+        Ok(b) => b,
+        Err(e) => return Err(RepoError::GitErr(e, format!("write blob of {}", file)))
+    };
+
+    return match tree.add(&blob_index_entry(file.as_bytes(), blob, BLOB_FILE_MODE, contents.len())) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(RepoError::GitErr(e, format!("add {} to in-memory tree", file)))
+    };
+}
+
+fn in_memory_remove(tree: &mut Index, file: &str) -> Result<bool, RepoError> {
+    if tree.get_path(Path::new(file), 0).is_none() { return Ok(false); }
+
+    return match tree.remove_path(Path::new(file)) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(RepoError::GitErr(e, format!("remove {} from in-memory tree", file)))
+    };
+}
+
+fn in_memory_read(repo: &Repository, tree: &Index, file: &str) -> Result<String, RepoError> {
+    let blob_id = match tree.get_path(Path::new(file), 0) {
+        Some(entry) => entry.id,
+        None => return Err(RepoError::FileInvalid { repo: repo_errname(repo), file: file.to_string(), reason: String::from("file doesn't exist") })
+    };
+
+    let blob = match repo.find_blob(blob_id) {
+        Ok(b) => b,
+        Err(e) => return Err(RepoError::GitErr(e, format!("read blob of {}", file)))
+    };
+
+    return match String::from_utf8(blob.content().to_vec()) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(RepoError::FailRead { err: io::Error::new(io::ErrorKind::InvalidData, e), repo: repo_errname(repo), file: file.to_string() })
+    };
+}
+
+fn workdir_file_exists(repo: &Repository, file: &str) -> Result<bool, RepoError> {
+    return Ok(get_repo_file_path(repo, file)?.is_file());
+}
+
+fn workdir_non_file_exists(repo: &Repository, file: &str) -> Result<bool, RepoError> {
+    let target_path = get_repo_file_path(repo, file)?;
+    return Ok(target_path.exists() && !target_path.is_file());
+}
+
+fn workdir_overwrite_file(repo: &Repository, file: &str, contents: &str) -> Result<(), RepoError> {
     let target_path = get_repo_file_path(repo, file)?;
     let target_valid = !target_path.exists() || target_path.is_file();
     
@@ -223,6 +467,15 @@ pub fn overwrite_file(repo: &Repository, file: &str, contents: &str) -> Result<(
         return Err(RepoError::FileInvalid { repo: repo_errname(repo), file: file.to_string(), reason: String::from("non-file item exists at path") });
     }
 
+    // Files of multi-file codes may be published into directories which don't exist yet
+    match target_path.parent() {
+        Some(parent) => match fs::create_dir_all(parent) {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::FailWrite { err: e, repo: repo_errname(repo), file: file.to_string() })
+        },
+        None => ()
+    };
+
     let mut file_handle = match fs::OpenOptions::new().create(true).write(true).truncate(true).open(target_path) {
         Ok(f) => f,
         Err(e) => return Err(RepoError::FailWrite { err: e, repo: repo_errname(repo), file: file.to_string() })
@@ -236,7 +489,17 @@ pub fn overwrite_file(repo: &Repository, file: &str, contents: &str) -> Result<(
     return Ok(())
 }
 
-pub fn read_file(repo: &Repository, file: &str) -> Result<String, RepoError> {
+fn workdir_remove_file(repo: &Repository, file: &str) -> Result<bool, RepoError> {
+    let target_path = get_repo_file_path(repo, file)?;
+    if !target_path.is_file() { return Ok(false); }
+
+    return match fs::remove_file(target_path) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(RepoError::FailWrite { err: e, repo: repo_errname(repo), file: file.to_string() })
+    };
+}
+
+fn workdir_read_file(repo: &Repository, file: &str) -> Result<String, RepoError> {
     let target_path = get_repo_file_path(repo, file)?;
     
     if !target_path.exists() {
@@ -278,49 +541,71 @@ pub fn read_file(repo: &Repository, file: &str) -> Result<String, RepoError> {
     return Ok(contents);
 }
 
-fn item_derive_recurse(repo: &Repository, item: &mut dyn RepoDerivable) -> Result<(), RepoError> {
-    let valid = item.repo_derive(repo);
+fn item_init_recurse(files: &mut RepoFiles, item: &mut dyn RepoDerivable, created: &mut Vec<String>) -> Result<(), RepoError> {
+    match item.repo_init(files)? {
+        Some(c) => created.push(c),
+        None => ()
+    };
+
+    let items = item.derivable_children();
+    if items.is_none() { return Ok(()); }
+
+    for i in items.unwrap() {
+        item_init_recurse(files, i, created)?;
+    }
+
+    return Ok(());
+}
+
+fn item_derive_recurse(files: &RepoFiles, item: &mut dyn RepoDerivable) -> Result<(), RepoError> {
+    let valid = item.repo_derive(files);
     let items = item.derivable_children();
     if valid.is_err() || items.is_none() { return valid; }
 
     for i in items.unwrap() {
-        item_derive_recurse(repo, i)?;
+        item_derive_recurse(files, i)?;
     }
 
     return Ok(());
 }
 
-fn item_process_recurse(repo: &Repository, item: &mut dyn RepoDerivable) -> Result<(), RepoError> {
-    let valid = item.repo_process(repo);
+fn item_process_recurse(files: &RepoFiles, item: &mut dyn RepoDerivable) -> Result<(), RepoError> {
+    let valid = item.repo_process(files);
     let items = item.derivable_children();
     if valid.is_err() || items.is_none() { return valid; }
 
     for i in items.unwrap() {
-        item_process_recurse(repo, i)?;
+        item_process_recurse(files, i)?;
     }
 
     return Ok(());
 }
 
-fn item_write_changes_recurse(repo: &Repository, item: &dyn RepoPublishable) -> Result<(), RepoError> {
-    let valid = item.repo_publish(repo);
+fn item_write_changes_recurse(files: &mut RepoFiles, item: &dyn RepoPublishable) -> Result<(), RepoError> {
+    let valid = item.repo_publish(files);
     let items = item.publishable_children();
     if valid.is_err() || items.is_none() { return valid; }
 
     for i in items.unwrap() {
-        item_write_changes_recurse(repo, i)?;
+        item_write_changes_recurse(files, i)?;
     }
 
     return Ok(());
 }
 
-fn items_valid_recurse(repo: &Repository, item: &dyn RepoPublishable) -> Result<(), RepoError> {
-    let valid = item.repo_valid(repo);
+/// Writes the item and all of its children to the repo, returning the repo relative path of every file written
+fn write_changes(files: &mut RepoFiles, item: &dyn RepoPublishable) -> Result<Vec<String>, RepoError> {
+    item_write_changes_recurse(files, item)?;
+    return Ok(files.written());
+}
+
+fn items_valid_recurse(files: &RepoFiles, item: &dyn RepoPublishable) -> Result<(), RepoError> {
+    let valid = item.repo_valid(files);
     let items = item.publishable_children();
     if valid.is_err() || items.is_none() { return valid; }
 
     for i in items.unwrap() {
-        items_valid_recurse(repo, i)?;
+        items_valid_recurse(files, i)?;
     }
 
     return valid;
@@ -352,115 +637,1085 @@ pub fn clone(url: &str, dest: impl AsRef<Path>) -> Result<(), RepoError> {
     };
 }
 
-pub fn publish(repo: &Repository, item: &mut impl RepoPublishable, author: Option<String>, author_email: Option<String>) -> Result<(), RepoError> {
-    let thread_log = cmterm::Log::get();
+/// Seeds default state for the item and all of its children, returning a description of everything created
+pub fn init(repo: &Repository, item: &mut dyn RepoDerivable) -> Result<Vec<String>, RepoError> {
+    let mut created = Vec::<String>::new();
+    item_init_recurse(&mut RepoFiles::new(repo), item, &mut created)?;
+    return Ok(created);
+}
 
-    let head = match repo.head() {
-        Ok(h) => h,
-        Err(_e) => return Err(RepoError::HeadCheckFailed(repo_errname(repo)))
-    };
+/// A file published by an item, along with the contents it will hold once published
+pub struct PublishTarget {
+    pub file: String,
+    pub data: String
+}
 
-    match repo.head_detached() {
-        Ok(detached) => if detached { return Err(RepoError::HeadDetached(repo_errname(repo))) },
-        Err(_) => return Err(RepoError::HeadCheckFailed(repo_errname(repo)))
-    };
+/// A file published by an item, along with the raw URL it can be retrieved from
+pub struct PublishedFile {
+    pub file: String,
+    pub url: String
+}
 
-    if !head.is_branch() {
-        return Err(RepoError::HeadNotBranch(repo_errname(repo)));
+/// The result of a successful publish
+pub struct PublishOutcome {
+    /// The commit holding the published item
+    pub commit: Oid,
+
+    /// Every file published by the item, in the order given by publish_targets
+    pub files: Vec<PublishedFile>,
+
+    /// True if the item's target files already held the item's data, and so no commit was made
+    pub unchanged: bool
+}
+
+/// Git config entry choosing how a publish treats changes in the workdir it didn't make
+const DIRTY_POLICY_CONFIG: &'static str = "codeless.dirtyPolicy";
+
+/// How a publish treats changes in the workdir it didn't make, which are never committed
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DirtyPolicy {
+    /// Publish anyway, warning which changes were left out
+    #[default]
+    Warn,
+
+    /// Refuse to publish before anything is written
+    Refuse
+}
+
+impl FromStr for DirtyPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.to_lowercase().as_str() {
+            "warn" => Ok(DirtyPolicy::Warn),
+            "refuse" => Ok(DirtyPolicy::Refuse),
+            _ => Err(format!("expected one of warn, refuse, got \"{}\"", s))
+        };
     }
+}
 
-    let parent_commit = match head.peel_to_commit() {
+/// Reads the repo's dirty policy from git config, defaulting to warn if unset
+fn dirty_policy(repo: &Repository) -> Result<DirtyPolicy, RepoError> {
+    let config = match repo.config() {
         Ok(c) => c,
-        Err(e) => return Err(RepoError::GitErr(e, String::from("resolve HEAD to commit")))
+        Err(e) => return Err(RepoError::GitErr(e, String::from("open repo config")))
     };
 
-    thread_log.log("Validating repository state...");
-    items_valid_recurse(repo, item)?;
+    let policy = match config.get_string(DIRTY_POLICY_CONFIG) {
+        Ok(p) => p,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(DirtyPolicy::default()),
+        Err(e) => return Err(RepoError::GitErr(e, format!("read {} from config", DIRTY_POLICY_CONFIG)))
+    };
+
+    return match policy.parse::<DirtyPolicy>() {
+        Ok(p) => Ok(p),
+        Err(e) => Err(RepoError::PublishError(format!("{} config was invalid: {}", DIRTY_POLICY_CONFIG, e)))
+    };
+}
+
+/// Stages the current state of each file, staging its removal if it no longer exists
+fn stage_files(repo: &Repository, index: &mut Index, files: &[String]) -> Result<(), RepoError> {
+    for file in files {
+        let staged = match get_repo_file_path(repo, file)?.is_file() {
+            true => index.add_path(Path::new(file)),
+            false => index.remove_path(Path::new(file))
+        };
+
+        match staged {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::GitErr(e, format!("stage {}", file)))
+        };
+    }
+
+    return Ok(());
+}
 
+/// Stages the files within the index on disk, leaving anything else staged there as it was
+fn sync_index(repo: &Repository, files: &[String]) -> Result<(), RepoError> {
     let mut index = get_index(repo)?;
+    match index.read(true) {
+        Ok(_) => (),
+        Err(e) => return Err(RepoError::GitErr(e, String::from("reset index to state on disk")))
+    };
 
+    stage_files(repo, &mut index, files)?;
+
+    return match index.write() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(RepoError::GitErr(e, String::from("write index")))
+    };
+}
+
+/// Updates the index on disk to match the new tree for every file changed between the trees, leaving the workdir untouched
+fn sync_index_to_tree(repo: &Repository, old_tree: &Tree, new_tree: &Tree) -> Result<(), RepoError> {
+    let diff = match repo.diff_tree_to_tree(Some(old_tree), Some(new_tree), None) {
+        Ok(d) => d,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("diff published trees")))
+    };
+
+    let mut index = get_index(repo)?;
     match index.read(true) {
         Ok(_) => (),
         Err(e) => return Err(RepoError::GitErr(e, String::from("reset index to state on disk")))
     };
 
+    for delta in diff.deltas() {
+        let new_file = delta.new_file();
+        let path = match new_file.path() {
+            Some(p) => p,
+            None => continue
+        };
+
+        let staged = match new_file.exists() {
+            true => index.add(&blob_index_entry(path.to_string_lossy().as_bytes(), new_file.id(), u32::from(new_file.mode()), new_file.size() as usize)),
+            false => index.remove_path(path)
+        };
+
+        match staged {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::GitErr(e, format!("stage {}", path.display())))
+        };
+    }
+
+    return match index.write() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(RepoError::GitErr(e, String::from("write index")))
+    };
+}
+
+/// Reads whether the repo is configured to publish in memory, defaulting to false if unset
+fn in_memory_configured(repo: &Repository) -> Result<bool, RepoError> {
+    let config = match repo.config() {
+        Ok(c) => c,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("open repo config")))
+    };
+
+    return match config.get_bool(IN_MEMORY_CONFIG) {
+        Ok(b) => Ok(b),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(e) => Err(RepoError::GitErr(e, format!("read {} from config", IN_MEMORY_CONFIG)))
+    };
+}
+
+/// Every changed or untracked file in the workdir
+fn workdir_changes(repo: &Repository) -> Result<Vec<String>, RepoError> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+
+    let statuses = match repo.statuses(Some(&mut options)) {
+        Ok(s) => s,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("read workdir status")))
+    };
+
+    return Ok(statuses.iter()
+        .filter(|s| { s.status() != Status::CURRENT })
+        .filter_map(|s| { s.path().map(|p| { p.to_string() }) })
+        .collect());
+}
+
+/// Git config entries of the identity publishes are committed under when user.name or user.email are unset
+const IDENTITY_FALLBACK_NAME_CONFIG: &'static str = "codeless.fallbackName";
+const IDENTITY_FALLBACK_EMAIL_CONFIG: &'static str = "codeless.fallbackEmail";
+
+/// Identity committed under when neither git config entry is set
+const DEFAULT_IDENTITY_NAME: &'static str = "Codeless Mission Uploader";
+const DEFAULT_IDENTITY_EMAIL: &'static str = "codeless-mission-uploader@localhost";
+
+/// Email of authors credited by mission codes without one, so the commit isn't attributed to the committer's account
+const UNKNOWN_AUTHOR_EMAIL: &'static str = "unknown-author@localhost";
+
+/// Name & email publish commits are made under
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PublishIdentity {
+    pub name: String,
+    pub email: String
+}
+
+impl Display for PublishIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.write_fmt(format_args!("{} <{}>", self.name, self.email));
+    }
+}
+
+/// Reads the first of the given config entries which is set & non-empty, along with its name
+fn first_config_string(config: &git2::Config, entries: &[&'static str]) -> Result<Option<(String, &'static str)>, RepoError> {
+    for entry in entries {
+        match config.get_string(entry) {
+            Ok(v) if !v.trim().is_empty() => return Ok(Some((v.trim().to_string(), *entry))),
+            Ok(_) => (),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => (),
+            Err(e) => return Err(RepoError::GitErr(e, format!("read {} from config", entry)))
+        };
+    }
+
+    return Ok(None);
+}
+
+/// Resolves the identity publishes to the repo are committed under from user.name & user.email in git config,
+/// so a repo's own config overrides the global identity
+/// Unset entries fall back to codeless.fallbackName & codeless.fallbackEmail, then to the uploader's defaults, logging a warning
+pub fn publish_identity(repo: &Repository, log: &cmterm::Log) -> Result<PublishIdentity, RepoError> {
+    return match repo.config() {
+        Ok(c) => config_identity(&c, log),
+        Err(e) => Err(RepoError::GitErr(e, String::from("open repo config")))
+    };
+}
+
+/// Resolves the publish identity from an already opened config, see publish_identity
+fn config_identity(config: &git2::Config, log: &cmterm::Log) -> Result<PublishIdentity, RepoError> {
+    let mut identity = PublishIdentity { name: String::from(DEFAULT_IDENTITY_NAME), email: String::from(DEFAULT_IDENTITY_EMAIL) };
+    let fields = [
+        (&mut identity.name, ["user.name", IDENTITY_FALLBACK_NAME_CONFIG]),
+        (&mut identity.email, ["user.email", IDENTITY_FALLBACK_EMAIL_CONFIG])
+    ];
+
+    for (field, entries) in fields {
+        match first_config_string(config, &entries)? {
+            Some((value, entry)) => {
+                if entry != entries[0] {
+                    log.log_warn(format!("{} is not set in git config, using {} \"{}\" instead", entries[0], entry, value));
+                }
+                *field = value;
+            },
+            None => log.log_warn(format!("Neither {} nor {} are set in git config, using \"{}\" instead", entries[0], entries[1], field))
+        };
+    }
+
+    return Ok(identity);
+}
+
+/// Returns true if the commit's tree already holds the given data at the given path
+fn commit_file_matches(repo: &Repository, commit: &Commit, file: &str, data: &[u8]) -> Result<bool, RepoError> {
+    let tree = match commit.tree() {
+        Ok(t) => t,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("resolve commit to tree")))
+    };
+
+    let entry = match tree.get_path(Path::new(file)) {
+        Ok(e) => e,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(false),
+        Err(e) => return Err(RepoError::GitErr(e, format!("find {} in commit tree", file)))
+    };
+
+    return match entry.to_object(repo).and_then(|o| { o.peel_to_blob() }) {
+        Ok(blob) => Ok(blob.content() == data),
+        // Non-blob item at path, let validation report it
+        Err(_) => Ok(false)
+    };
+}
+
+fn raw_file_url(remote: &Remote, commit: Oid, file: &str) -> String {
+    return format!(
+        "{}/raw/{}/{}", 
+        remote.url().expect("remote URL should be valid").replace("gist.github.com", "gist.githubusercontent.com").trim_end_matches("/"),
+        commit.to_string(),
+        file
+    );
+}
+
+fn published_files(remote: &Remote, commit: Oid, targets: &[PublishTarget]) -> Vec<PublishedFile> {
+    return targets.iter().map(|t| {
+        PublishedFile { file: t.file.clone(), url: raw_file_url(remote, commit, &t.file) }
+    }).collect();
+}
+
+fn copy_to_clipboard(url: &str) {
+    let thread_log = cmterm::Log::get();
+    thread_log.log("Copying link to clipboard...");
+
+    match crate::clipboard::set_text(url) {
+        Ok(_) => thread_log.log_success("Copied link to clipboard"),
+        // The link is still returned to the caller, so this isn't worth failing the publish over
+        Err(e) => thread_log.log_warn(format!("Error whilst copying to clipboard {:?}\nThe link will have to be copied manually", e))
+    }
+}
+
+fn git_authenticator() -> auth_git2::GitAuthenticator {
+    return auth_git2::GitAuthenticator::new().set_prompter(LogHandle::new(cmterm::Log::get()))
+                                     .add_default_ssh_keys()
+                                     .try_cred_helper(true)
+                                     .try_ssh_agent(true)
+                                     .try_password_prompt(1)
+                                     .prompt_ssh_key_password(true);
+}
+
+/// Fetches the branch from the remote, returning the commit to fast-forward to if the remote is ahead
+/// Errors if the branch has diverged from the remote, as publishing on top of it would be rejected
+fn fetch_remote_branch(repo: &Repository, remote_name: &str, branch: &str) -> Result<Option<Oid>, RepoError> {
+    let thread_log = cmterm::Log::get();
+
+    let branch_name = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    let tracking_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+
+    let mut remote = match repo.find_remote(remote_name) {
+        Ok(r) => r,
+        Err(e) => return Err(RepoError::GitErr(e, format!("find remote {}", remote_name)))
+    };
+
+    thread_log.log(format!("Fetching {} from {}...", branch_name, remote_name));
+    match git_authenticator().fetch(repo, &mut remote, &[&format!("+{}:{}", branch, tracking_ref)], None) {
+        Ok(_) => (),
+        Err(e) => return Err(RepoError::GitErr(e, format!("fetch {} from {}", branch_name, remote_name)))
+    };
+
+    let remote_oid = match repo.refname_to_id(&tracking_ref) {
+        Ok(oid) => oid,
+        // Nothing has been published to the remote yet
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(RepoError::GitErr(e, format!("resolve {}", tracking_ref)))
+    };
+
+    let local_oid = match repo.refname_to_id(branch) {
+        Ok(oid) => oid,
+        Err(e) => return Err(RepoError::GitErr(e, format!("resolve {}", branch)))
+    };
+
+    if local_oid == remote_oid { return Ok(None); }
+
+    let (ahead, behind) = match repo.graph_ahead_behind(local_oid, remote_oid) {
+        Ok(ab) => ab,
+        Err(e) => return Err(RepoError::GitErr(e, format!("compare {} with {}", branch, tracking_ref)))
+    };
+
+    return match (ahead, behind) {
+        (_, 0) => Ok(None),
+        (0, _) => Ok(Some(remote_oid)),
+        (ahead, behind) => Err(RepoError::PublishError(format!(
+            "{} has diverged from {}/{} by {} local & {} remote commits, these must be reconciled manually",
+            branch_name, remote_name, branch_name, ahead, behind
+        )))
+    };
+}
+
+/// Fast-forwards the branch, and the working files unless publishing in memory, to the fetched remote commit
+fn fast_forward(repo: &Repository, remote_name: &str, branch: &str, remote_oid: Oid, in_memory: bool) -> Result<(), RepoError> {
+    let thread_log = cmterm::Log::get();
+    let branch_name = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+
+    let local_oid = match repo.refname_to_id(branch) {
+        Ok(oid) => oid,
+        Err(e) => return Err(RepoError::GitErr(e, format!("resolve {}", branch)))
+    };
+
+    let remote_commit = match repo.find_object(remote_oid, None) {
+        Ok(o) => o,
+        Err(e) => return Err(RepoError::GitErr(e, format!("find commit {}", remote_oid)))
+    };
+
+    // Safe checkout refuses to overwrite local modifications, rather than discarding them
+    if !in_memory {
+        match repo.checkout_tree(&remote_commit, Some(CheckoutBuilder::new().safe())) {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::GitErr(e, format!("fast-forward working files to {}", remote_oid)))
+        };
+    }
+
+    let fast_forward = repo.find_reference(branch).and_then(|mut r| {
+        r.set_target(remote_oid, &format!("codeless: fast-forward to {}/{}", remote_name, branch_name))
+    });
+    match fast_forward {
+        Ok(_) => (),
+        Err(e) => return Err(RepoError::GitErr(e, format!("fast-forward {} to {}", branch, remote_oid)))
+    };
+
+    if in_memory && !repo.is_bare() {
+        let trees = repo.find_commit(local_oid).and_then(|c| { c.tree() })
+            .and_then(|local| { remote_commit.peel_to_tree().map(|remote| { (local, remote) }) });
+        let synced = match trees {
+            Ok((local_tree, remote_tree)) => sync_index_to_tree(repo, &local_tree, &remote_tree),
+            Err(e) => Err(RepoError::GitErr(e, format!("resolve trees of {} & {}", local_oid, remote_oid)))
+        };
+        match synced {
+            Ok(_) => (),
+            Err(e) => thread_log.log_warn(format!("Failed to update the index to {}, fast-forwarded files may show as modified\n{}", remote_oid, e))
+        };
+    }
+
+    thread_log.log_success(format!("Fast-forwarded {} to {}/{} at {}", branch_name, remote_name, branch_name, remote_oid));
+    return Ok(());
+}
+
+/// Restores the branch and working files to their state before a failed publish, logging what was restored
+/// Failures are logged rather than returned, so the error which caused the rollback is still reported
+fn rollback(files: &RepoFiles, context: &PublishContext, committed: Option<Oid>) {
+    let thread_log = cmterm::Log::get();
+    thread_log.log_warn("Publish failed, rolling back repository changes...");
+
+    let repo = files.repo();
+    let (branch, parent_commit) = (context.branch, context.parent_commit);
+    match committed {
+        Some(commit_oid) => {
+            let reset = repo.find_reference(branch).and_then(|mut r| {
+                r.set_target(parent_commit.id(), &format!("codeless: roll back failed publish of {}", commit_oid))
+            });
+            match reset {
+                Ok(_) => thread_log.log_warn(format!("Reset {} from unpushed commit {} to {}", branch, commit_oid, parent_commit.id())),
+                Err(e) => thread_log.log_err(format!("Failed to reset {} to {}, it must be reset manually\n{}", branch, parent_commit.id(), e))
+            };
+        },
+        None => ()
+    };
+
+    // Nothing was written to the workdir, so there are no working files to restore
+    let workdir = match files.workdir() {
+        Some(w) => w,
+        None => return
+    };
+
+    let parent_tree = parent_commit.tree().ok();
+    let mut restored = 0;
+    // Reversed so files journaled as Written take precedence over External entries for the same file
+    for entry in files.journal.iter().rev() {
+        let prior = match entry {
+            JournalEntry::Written { prior, .. } => prior.clone(),
+            JournalEntry::External { file } => parent_tree.as_ref()
+                .and_then(|t| { t.get_path(Path::new(file)).ok() })
+                .and_then(|e| { e.to_object(repo).ok() })
+                .and_then(|o| { o.peel_to_blob().ok() })
+                .map(|b| { b.content().to_vec() })
+        };
+
+        let target_path = workdir.join(entry.file());
+        let result = match prior {
+            Some(contents) => fs::write(&target_path, contents),
+            None if target_path.is_file() => fs::remove_file(&target_path),
+            None => Ok(())
+        };
+
+        match result {
+            Ok(_) => restored += 1,
+            Err(e) => thread_log.log_err(format!("Failed to restore {}, it must be restored manually\n{}", entry.file(), e))
+        };
+    }
+
+    thread_log.log_warn(format!("Rolled back {} of {} written files", restored, files.journal.len()));
+}
+
+/// Builds the tree of the parent tree with the current state of each written file, staging its removal if it no longer exists
+/// Built in a fresh index rather than the repo's, so neither changes staged by the user nor a failed publish leak into it
+fn written_files_tree(repo: &Repository, parent_tree: &Tree, written: &[String]) -> Result<Oid, RepoError> {
+    let mut index = match Index::new() {
+        Ok(i) => i,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("create publish index")))
+    };
+
+    match index.read_tree(parent_tree) {
+        Ok(_) => (),
+        Err(e) => return Err(RepoError::GitErr(e, String::from("read parent commit into publish index")))
+    };
+
+    for file in written {
+        let target_path = get_repo_file_path(repo, file)?;
+        if !target_path.is_file() {
+            match index.remove_path(Path::new(file)) {
+                Ok(_) => continue,
+                Err(e) => return Err(RepoError::GitErr(e, format!("stage removal of {}", file)))
+            };
+        }
+
+        let contents = match fs::read(&target_path) {
+            Ok(c) => c,
+            Err(e) => return Err(RepoError::FailRead { err: e, repo: repo_errname(repo), file: file.to_string() })
+        };
+
+        let blob = match repo.blob(&contents) {
+            Ok(b) => b,
+            Err(e) => return Err(RepoError::GitErr(e, format!("write blob of {}", file)))
+        };
+
+        // Keeps the mode of files which were already tracked, such as executables written by external commands
+        let mode = index.get_path(Path::new(file), 0).map(|e| { e.mode }).unwrap_or(BLOB_FILE_MODE);
+        match index.add(&blob_index_entry(file.as_bytes(), blob, mode, contents.len())) {
+            Ok(_) => (),
+            Err(e) => return Err(RepoError::GitErr(e, format!("stage {}", file)))
+        };
+    }
+
+    return match index.write_tree_to(repo) {
+        Ok(oid) => Ok(oid),
+        Err(e) => Err(RepoError::GitErr(e, String::from("write publish index to tree")))
+    };
+}
+
+/// Where & as whom a publish commits
+struct PublishContext<'c> {
+    branch: &'c str,
+    target_remote: &'c str,
+    parent_commit: &'c Commit<'c>,
+    author: PublishIdentity,
+    committer: PublishIdentity
+}
+
+/// Writes, commits & pushes the item, recording the commit in committed once it is made so a failed push can be rolled back
+fn publish_transaction<'r>(
+    files: &mut RepoFiles<'r>,
+    item: &mut (impl RepoPublishable + RepoDerivable),
+    context: &PublishContext,
+    committed: &mut Option<Oid>
+) -> Result<(Oid, Remote<'r>), RepoError> {
+    let thread_log = cmterm::Log::get();
+    let repo = files.repo();
+
     match item.derivable_children() {
         Some(mut v) => {
+            let mut created = Vec::<String>::new();
+            for d in v.iter_mut() {
+                item_init_recurse(files, *d, &mut created)?;
+            }
+
+            for c in created {
+                thread_log.log_success(format!("Initialized missing repository state {}", c));
+            }
+
             thread_log.log("Deriving repository items...");
             for d in v.iter_mut() {
-                item_derive_recurse(repo, *d)?;
+                item_derive_recurse(files, *d)?;
             }
 
             thread_log.log("Processing repository items...");
             for d in v.iter_mut() {
-                item_process_recurse(repo, *d)?;
+                item_process_recurse(files, *d)?;
             }
         }
         None => ()
     };
 
+    item.repo_process(files)?;
+
     thread_log.log("Publishing repository items...");
-    item_write_changes_recurse(repo, item).unwrap();
+    let written = write_changes(files, item)?;
 
-    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None).unwrap();
-    let index_tree_oid = index.write_tree().unwrap();
+    let parent_tree = match context.parent_commit.tree() {
+        Ok(t) => t,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("resolve parent commit tree")))
+    };
 
-    let index_tree = repo.find_tree(index_tree_oid).unwrap();
+    let index_tree_oid = match &mut files.storage {
+        FileStorage::Workdir => written_files_tree(repo, &parent_tree, &written)?,
+        FileStorage::InMemory(tree) => match tree.write_tree_to(repo) {
+            Ok(oid) => oid,
+            Err(e) => return Err(RepoError::GitErr(e, String::from("write in-memory tree")))
+        }
+    };
 
-    let author = Signature::now(
-        &author.unwrap_or(String::from("Codeless Mission Uploader")),
-        &author_email.unwrap_or(String::from("91488389+Sprixitite@users.noreply.github.com"))
-    ).unwrap();
+    let index_tree = match repo.find_tree(index_tree_oid) {
+        Ok(t) => t,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("write index to tree")))
+    };
 
-    let committer = Signature::now("Codeless Mission Uploader", "91488389+Sprixitite@users.noreply.github.com").unwrap();
+    let committer = match Signature::now(&context.committer.name, &context.committer.email) {
+        Ok(s) => s,
+        Err(e) => return Err(RepoError::GitErr(e, format!("create committer signature for {}", context.committer)))
+    };
+
+    let author = match Signature::now(&context.author.name, &context.author.email) {
+        Ok(s) => s,
+        Err(e) => return Err(RepoError::GitErr(e, format!("create author signature for {}", context.author)))
+    };
 
-    let commit_oid = repo.commit(Some("HEAD"), &author, &committer, &item.publish_message(), &index_tree, &[&parent_commit]).unwrap();
+    let commit_oid = match repo.commit(Some("HEAD"), &author, &committer, &item.publish_message(), &index_tree, &[context.parent_commit]) {
+        Ok(oid) => oid,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("commit published files")))
+    };
+    *committed = Some(commit_oid);
     thread_log.log(format!("Commit Oid: {}", commit_oid.to_string()));
     
     // TODO: Is this even valid?
     // head.set_target(commit_oid, &item.publish_message())?;
 
-    let target_remote = item.publish_target_remote(repo)?;
-    let mut remote = repo.find_remote(&target_remote).unwrap();
+    let mut remote = match repo.find_remote(context.target_remote) {
+        Ok(r) => r,
+        Err(e) => return Err(RepoError::GitErr(e, format!("find remote {}", context.target_remote)))
+    };
     
     // let mut cred_helper = git2::CredentialHelper::new(remote.url().unwrap());
     // cred_helper.config( git2::Config:: )
 
     // let a = PushOptions::new();
     // let b = RemoteCallbacks::new();
-    let git_auth = auth_git2::GitAuthenticator::new().set_prompter(LogHandle::new(thread_log.clone()))
-                                     .add_default_ssh_keys()
-                                     .try_cred_helper(true)
-                                     .try_ssh_agent(true)
-                                     .try_password_prompt(1)
-                                     .prompt_ssh_key_password(true);
-
-    match git_auth.push(repo, &mut remote, &[head.name().unwrap()]) {
+    match git_authenticator().push(repo, &mut remote, &[context.branch]) {
         Ok(_) => (),
         Err(e) => return Err(RepoError::GitErr(e, String::from("when pushing to remote")))
     };
 
-    match index.clear() {
+    // The commit is already pushed, so failing to update the index on disk shouldn't roll it back
+    let index_synced = match files.storage {
+        FileStorage::Workdir => sync_index(repo, &written),
+        FileStorage::InMemory(_) if repo.is_bare() => Ok(()),
+        FileStorage::InMemory(_) => sync_index_to_tree(repo, &parent_tree, &index_tree)
+    };
+    match index_synced {
         Ok(_) => (),
-        Err(e) => return Err(RepoError::GitErr(e, String::from("when clearing index")))
+        Err(e) => thread_log.log_warn(format!("Failed to stage published files in the index, they may show as modified\n{}", e))
     };
 
-    thread_log.log("Copying link to clipboard...");
+    return Ok((commit_oid, remote));
+}
 
-    let content_url = format!(
-        "{}/raw/{}/{}", 
-        remote.url().expect("remote URL should be valid").replace("gist.github.com", "gist.githubusercontent.com").trim_end_matches("/"),
-        commit_oid.to_string(),
-        item.publish_target_file()
-    );
+/// Publishes the item to its target remote
+/// 
+/// The item's derivable children are initialized, derived and processed before the item itself is processed,
+/// allowing the item to act upon the processed state of its children before anything is written
+///
+/// Bare repos, or those with `codeless.inMemory` set, are always published in memory,
+/// building the commit from HEAD without reading or writing the workdir
+///
+/// The URL of the first published file is copied to the clipboard if copy_url is set
+pub fn publish(
+    repo: &Repository,
+    item: &mut (impl RepoPublishable + RepoDerivable),
+    author: Option<String>,
+    author_email: Option<String>,
+    in_memory: bool,
+    copy_url: bool
+) -> Result<PublishOutcome, RepoError> {
+    let thread_log = cmterm::Log::get();
 
-    match crate::clipboard::set_text(content_url) {
-        Ok(_) => thread_log.log_success("Copied link to clipboard"),
+    let head = match repo.head() {
+        Ok(h) => h,
+        Err(_e) => return Err(RepoError::HeadCheckFailed(repo_errname(repo)))
+    };
+
+    match repo.head_detached() {
+        Ok(detached) => if detached { return Err(RepoError::HeadDetached(repo_errname(repo))) },
+        Err(_) => return Err(RepoError::HeadCheckFailed(repo_errname(repo)))
+    };
+
+    if !head.is_branch() {
+        return Err(RepoError::HeadNotBranch(repo_errname(repo)));
+    }
+
+    let branch = head.name().unwrap().to_string();
+
+    // Fetched before anything is derived, so features derive their state from what was last published
+    let target_remote = item.publish_target_remote(repo)?;
+    let in_memory = in_memory || repo.is_bare() || in_memory_configured(repo)?;
+    if in_memory {
+        thread_log.log("Publishing in memory, the working directory will be left untouched");
+    }
+    let fast_forward_to = fetch_remote_branch(repo, &target_remote, &branch)?;
+
+    // Only fast-forwarded once the publish has been validated, so the commit is published onto the remote's instead
+    let parent_commit = match fast_forward_to {
+        Some(oid) => repo.find_commit(oid),
+        None => head.peel_to_commit()
+    };
+    let parent_commit = match parent_commit {
+        Ok(c) => c,
+        Err(e) => return Err(RepoError::GitErr(e, String::from("resolve parent commit")))
+    };
+
+    let files = match in_memory {
+        true => match parent_commit.tree() {
+            Ok(t) => RepoFiles::in_memory(repo, &t)?,
+            Err(e) => return Err(RepoError::GitErr(e, String::from("resolve HEAD to tree")))
+        },
+        false => RepoFiles::new(repo)
+    };
+
+    let committer = publish_identity(repo, &thread_log)?;
+    thread_log.log(format!("Committing as {}", committer));
+
+    // Mission codes may credit another author, otherwise the committer is the author
+    let author = match author {
+        Some(name) => PublishIdentity { name: name, email: author_email.unwrap_or(String::from(UNKNOWN_AUTHOR_EMAIL)) },
+        None => committer.clone()
+    };
+    let context = PublishContext { branch: &branch, target_remote: &target_remote, parent_commit: &parent_commit, author: author, committer: committer };
+
+    return publish_onto(files, item, &context, fast_forward_to, copy_url);
+}
+
+/// Validates, writes, commits & pushes the item onto the parent commit, rolling back if the publish fails
+/// The branch is only fast-forwarded to the parent commit once the publish is known to go ahead
+fn publish_onto(
+    mut files: RepoFiles,
+    item: &mut (impl RepoPublishable + RepoDerivable),
+    context: &PublishContext,
+    fast_forward_to: Option<Oid>,
+    copy_url: bool
+) -> Result<PublishOutcome, RepoError> {
+    let thread_log = cmterm::Log::get();
+    let repo = files.repo();
+    let parent_commit = context.parent_commit;
+
+    thread_log.log("Validating repository state...");
+    items_valid_recurse(&files, item)?;
+
+    let targets = item.publish_targets()?;
+    let mut unchanged = true;
+    for target in &targets {
+        if !commit_file_matches(repo, parent_commit, &target.file, target.data.as_bytes())? {
+            unchanged = false;
+            break;
+        }
+    }
+
+    if unchanged {
+        thread_log.log_success(format!("Published files are unchanged since commit {}, skipping publish", parent_commit.id()));
+
+        let extras = item.publish_extras();
+        if !extras.is_empty() {
+            thread_log.log_warn(format!("Skipped publishing {}, change the mission data to publish them", extras.join(", ")));
+        }
+
+        let remote = match repo.find_remote(context.target_remote) {
+            Ok(r) => r,
+            Err(e) => return Err(RepoError::GitErr(e, format!("find remote {}", context.target_remote)))
+        };
+
+        let published = published_files(&remote, parent_commit.id(), &targets);
+        if copy_url { copy_to_clipboard(&published[0].url); }
+
+        return Ok(PublishOutcome { commit: parent_commit.id(), files: published, unchanged: true });
+    }
+
+    // Checked before anything is written, so every change in the workdir is unrelated to the publish
+    let unrelated = match files.workdir() {
+        Some(_) => workdir_changes(repo)?,
+        None => Vec::new()
+    };
+    let dirty_policy = dirty_policy(repo)?;
+    if dirty_policy == DirtyPolicy::Refuse && !unrelated.is_empty() {
+        return Err(RepoError::PublishError(format!(
+            "workdir has {} changes unrelated to the publish, commit or discard them, or set {} to warn:\n{}",
+            unrelated.len(), DIRTY_POLICY_CONFIG, unrelated.join("\n")
+        )));
+    }
+
+    match fast_forward_to {
+        Some(oid) => fast_forward(repo, context.target_remote, context.branch, oid, files.workdir().is_none())?,
+        None => ()
+    };
+
+    let mut committed = None;
+    let (commit_oid, remote) = match publish_transaction(&mut files, item, context, &mut committed) {
+        Ok(r) => r,
         Err(e) => {
-            thread_log.log_err(format!("Error whilst copying to clipboard {:?}", e));
-            panic!("Sprix couldn't be bothered implementing proper error handling for this and would just like to eat")
+            rollback(&files, context, committed);
+            return Err(e);
         }
+    };
+
+    // Changes to files the publish went on to write were committed along with it
+    let written = files.written();
+    let unrelated = unrelated.into_iter().filter(|f| { !written.contains(f) }).collect::<Vec<String>>();
+    if !unrelated.is_empty() {
+        thread_log.log_warn(format!("Left {} changes unrelated to the publish uncommitted:\n{}", unrelated.len(), unrelated.join("\n")));
     }
 
-    return Ok(());
-}
\ No newline at end of file
+    let published = published_files(&remote, commit_oid, &targets);
+    if copy_url { copy_to_clipboard(&published[0].url); }
+
+    return Ok(PublishOutcome { commit: commit_oid, files: published, unchanged: false });
+}
+
+/// Helpers for tests publishing to temporary repos, shared with the tests of features
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use git2::build::RepoBuilder;
+    use crate::{features, mission_codes::MissionCode};
+
+    const MISSION_FILE: &'static str = "mission.txt";
+    const VERSION_TABLE_FILE: &'static str = ".custommissionversions.json";
+
+    pub(crate) fn setup() -> tempfile::TempDir {
+        cmterm::Log::set(Arc::new(cmterm::Log::new_headless("Test")));
+        features::register_builtin();
+        return tempfile::tempdir().unwrap();
+    }
+
+    /// Creates a bare remote holding a single commit of the given files
+    pub(crate) fn init_remote(dir: &Path, files: &[(&str, &str)]) -> PathBuf {
+        let remote_path = dir.join("remote.git");
+        let remote = Repository::init_bare(&remote_path).unwrap();
+
+        let mut index = Index::new().unwrap();
+        for (file, contents) in files {
+            let blob = remote.blob(contents.as_bytes()).unwrap();
+            index.add(&blob_index_entry(file.as_bytes(), blob, BLOB_FILE_MODE, contents.len())).unwrap();
+        }
+
+        let tree = remote.find_tree(index.write_tree_to(&remote).unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@localhost").unwrap();
+        remote.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+        return remote_path;
+    }
+
+    pub(crate) fn clone(remote_path: &Path, dest: &Path, bare: bool) -> Repository {
+        let repo = RepoBuilder::new().bare(bare).clone(remote_path.to_str().unwrap(), dest).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Publisher").unwrap();
+        config.set_str("user.email", "publisher@localhost").unwrap();
+        return repo;
+    }
+
+    fn publish_data(repo: &Repository, data: &str, in_memory: bool) -> Result<PublishOutcome, RepoError> {
+        let code = format!("_infilengine_cm_codeless_|1|1:1|14:MissionVersion|11:{}|4:None|6:origin|{}:{}", MISSION_FILE, data.len(), data);
+        let mut mission_code = MissionCode::parse_from(&code).unwrap();
+        let (author, author_email) = mission_code.commit_author();
+        return publish(repo, &mut mission_code, author, author_email, in_memory, false);
+    }
+
+    pub(crate) fn head_of(repo: &Repository) -> Oid {
+        return repo.head().unwrap().target().unwrap();
+    }
+
+    pub(crate) fn commit_file(repo: &Repository, commit: Oid, file: &str) -> Option<String> {
+        let tree = repo.find_commit(commit).unwrap().tree().unwrap();
+        return tree.get_path(Path::new(file)).ok().map(|e| {
+            String::from_utf8(e.to_object(repo).unwrap().peel_to_blob().unwrap().content().to_vec()).unwrap()
+        });
+    }
+
+    pub(crate) fn read_workdir(repo: &Repository, file: &str) -> Option<String> {
+        return fs::read_to_string(repo.workdir().unwrap().join(file)).ok();
+    }
+
+    /// Commits the file from another clone of the remote and pushes it, putting the remote ahead of existing clones
+    fn push_elsewhere(remote_path: &Path, dest: &Path, file: &str, contents: &str) -> Oid {
+        let other = clone(remote_path, dest, false);
+        fs::write(other.workdir().unwrap().join(file), contents).unwrap();
+
+        let mut index = other.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+
+        let tree = other.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Other", "other@localhost").unwrap();
+        let parent = other.find_commit(head_of(&other)).unwrap();
+        let pushed = other.commit(Some("HEAD"), &signature, &signature, "Edit elsewhere", &tree, &[&parent]).unwrap();
+
+        let branch = other.head().unwrap().name().unwrap().to_string();
+        other.find_remote("origin").unwrap().push(&[&branch], None).unwrap();
+        return pushed;
+    }
+
+    #[test]
+    fn init_creates_missing_state() {
+        let dir = setup();
+        let repo = Repository::init(dir.path().join("fresh")).unwrap();
+        fs::write(repo.workdir().unwrap().join(".custommissionsemver"), "1.2.3").unwrap();
+
+        let mut created = Vec::<String>::new();
+        for mut feature in features::construct_all_registered() {
+            created.append(&mut init(&repo, feature.as_mut()).unwrap());
+        }
+        assert_eq!(created.len(), 1);
+        assert_eq!(read_workdir(&repo, VERSION_TABLE_FILE).as_deref(), Some("{}"));
+        assert_eq!(read_workdir(&repo, ".custommissionsemver").as_deref(), Some("1.2.3"));
+
+        for mut feature in features::construct_all_registered() {
+            assert!(init(&repo, feature.as_mut()).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn identity_fallback_order() {
+        let dir = setup();
+        let log = cmterm::Log::get();
+        let mut config = git2::Config::new().unwrap();
+        config.add_file(&dir.path().join("config"), git2::ConfigLevel::Local, false).unwrap();
+
+        let identity = config_identity(&config, &log).unwrap();
+        assert_eq!(identity, PublishIdentity { name: String::from(DEFAULT_IDENTITY_NAME), email: String::from(DEFAULT_IDENTITY_EMAIL) });
+
+        config.set_str(IDENTITY_FALLBACK_NAME_CONFIG, "Fallback").unwrap();
+        config.set_str(IDENTITY_FALLBACK_EMAIL_CONFIG, "fallback@localhost").unwrap();
+        config.set_str("user.email", "  ").unwrap();
+        let identity = config_identity(&config, &log).unwrap();
+        assert_eq!(identity, PublishIdentity { name: String::from("Fallback"), email: String::from("fallback@localhost") });
+
+        config.set_str("user.name", "User").unwrap();
+        let identity = config_identity(&config, &log).unwrap();
+        assert_eq!(identity, PublishIdentity { name: String::from("User"), email: String::from("fallback@localhost") });
+    }
+
+    #[test]
+    fn push_failure_rolls_back() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let parent = head_of(&repo);
+
+        repo.remote_set_pushurl("origin", Some(dir.path().join("missing.git").to_str().unwrap())).unwrap();
+        assert!(publish_data(&repo, "new", false).is_err());
+
+        assert_eq!(head_of(&repo), parent);
+        assert_eq!(read_workdir(&repo, MISSION_FILE).as_deref(), Some("old"));
+        // Created by the publish, so removed by the rollback
+        assert_eq!(read_workdir(&repo, VERSION_TABLE_FILE), None);
+        // Neither the workdir nor the index hold anything from the failed publish
+        assert!(workdir_changes(&repo).unwrap().is_empty());
+    }
+
+    #[test]
+    fn author_without_email() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+
+        let outcome = publish(&repo, &mut MissionCode::parse_from(
+            "_infilengine_cm_codeless_|0|2|MissionVersion|Author=Sprix|mission.txt|None|origin|new"
+        ).unwrap(), Some(String::from("Sprix")), None, false, false).unwrap();
+
+        let commit = repo.find_commit(outcome.commit).unwrap();
+        assert_eq!(commit.author().name(), Some("Sprix"));
+        assert_eq!(commit.author().email(), Some(UNKNOWN_AUTHOR_EMAIL));
+        assert_eq!(commit.committer().name(), Some("Publisher"));
+        assert_eq!(commit.committer().email(), Some("publisher@localhost"));
+    }
+
+    #[test]
+    fn unchanged_publish_skipped() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+
+        let first = publish_data(&repo, "new", false).unwrap();
+        assert!(!first.unchanged);
+
+        let second = publish_data(&repo, "new", false).unwrap();
+        assert!(second.unchanged);
+        assert_eq!(second.commit, first.commit);
+        assert_eq!(second.files[0].url, first.files[0].url);
+        assert_eq!(head_of(&repo), first.commit);
+
+        let remote = Repository::open_bare(&remote_path).unwrap();
+        assert_eq!(head_of(&remote), first.commit);
+        assert_eq!(commit_file(&repo, first.commit, VERSION_TABLE_FILE).map(|t| { t.contains(": 1") }), Some(true));
+    }
+
+    #[test]
+    fn unrelated_changes_not_committed() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old"), ("other.txt", "other")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let parent = head_of(&repo);
+
+        fs::write(repo.workdir().unwrap().join("other.txt"), "staged").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("other.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(repo.workdir().unwrap().join("notes.txt"), "untracked").unwrap();
+
+        let outcome = publish_data(&repo, "new", false).unwrap();
+        assert!(!outcome.unchanged);
+        assert_eq!(repo.find_commit(outcome.commit).unwrap().parent_id(0).unwrap(), parent);
+        assert_eq!(commit_file(&repo, outcome.commit, MISSION_FILE).as_deref(), Some("new"));
+        assert!(commit_file(&repo, outcome.commit, VERSION_TABLE_FILE).is_some());
+        assert_eq!(commit_file(&repo, outcome.commit, "other.txt").as_deref(), Some("other"));
+        assert_eq!(commit_file(&repo, outcome.commit, "notes.txt"), None);
+
+        let remote = Repository::open_bare(&remote_path).unwrap();
+        assert_eq!(head_of(&remote), outcome.commit);
+
+        // Left as they were, with the published files staged alongside them
+        let mut changes = workdir_changes(&repo).unwrap();
+        changes.sort();
+        assert_eq!(changes, vec!["notes.txt", "other.txt"]);
+    }
+
+    #[test]
+    fn dirty_refuse_writes_nothing() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let parent = head_of(&repo);
+
+        repo.config().unwrap().set_str(DIRTY_POLICY_CONFIG, "refuse").unwrap();
+        fs::write(repo.workdir().unwrap().join("notes.txt"), "untracked").unwrap();
+        assert!(publish_data(&repo, "new", false).is_err());
+
+        assert_eq!(head_of(&repo), parent);
+        assert_eq!(read_workdir(&repo, MISSION_FILE).as_deref(), Some("old"));
+        assert_eq!(read_workdir(&repo, VERSION_TABLE_FILE), None);
+    }
+
+    #[test]
+    fn fetch_fast_forwards_before_publish() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old"), ("other.txt", "other")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let pushed = push_elsewhere(&remote_path, &dir.path().join("other"), "other.txt", "pushed elsewhere");
+
+        let outcome = publish_data(&repo, "new", false).unwrap();
+        assert_eq!(repo.find_commit(outcome.commit).unwrap().parent_id(0).unwrap(), pushed);
+        assert_eq!(commit_file(&repo, outcome.commit, "other.txt").as_deref(), Some("pushed elsewhere"));
+        assert_eq!(read_workdir(&repo, "other.txt").as_deref(), Some("pushed elsewhere"));
+    }
+
+    #[test]
+    fn refused_publish_not_fast_forwarded() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old"), ("other.txt", "other")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+        let parent = head_of(&repo);
+        push_elsewhere(&remote_path, &dir.path().join("other"), "other.txt", "pushed elsewhere");
+
+        repo.config().unwrap().set_str(DIRTY_POLICY_CONFIG, "refuse").unwrap();
+        fs::write(repo.workdir().unwrap().join("notes.txt"), "untracked").unwrap();
+        assert!(publish_data(&repo, "new", false).is_err());
+
+        assert_eq!(head_of(&repo), parent);
+        assert_eq!(read_workdir(&repo, "other.txt").as_deref(), Some("other"));
+        assert_eq!(read_workdir(&repo, MISSION_FILE).as_deref(), Some("old"));
+    }
+
+    #[test]
+    fn bare_publish_tree() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old"), ("other.txt", "other")]);
+        let repo = clone(&remote_path, &dir.path().join("mirror.git"), true);
+        let parent = head_of(&repo);
+
+        let outcome = publish_data(&repo, "new", false).unwrap();
+        assert_eq!(repo.find_commit(outcome.commit).unwrap().parent_id(0).unwrap(), parent);
+
+        let tree = repo.find_commit(outcome.commit).unwrap().tree().unwrap();
+        let mut files = tree.iter().map(|e| { e.name().unwrap().to_string() }).collect::<Vec<String>>();
+        files.sort();
+        assert_eq!(files, vec![VERSION_TABLE_FILE, MISSION_FILE, "other.txt"]);
+        assert_eq!(commit_file(&repo, outcome.commit, MISSION_FILE).as_deref(), Some("new"));
+        assert_eq!(commit_file(&repo, outcome.commit, "other.txt").as_deref(), Some("other"));
+
+        let versions = serde_json::from_str::<serde_json::Value>(&commit_file(&repo, outcome.commit, VERSION_TABLE_FILE).unwrap()).unwrap();
+        assert_eq!(versions, serde_json::json!({ MISSION_FILE: 1 }));
+
+        let remote = Repository::open_bare(&remote_path).unwrap();
+        assert_eq!(head_of(&remote), outcome.commit);
+    }
+
+    #[test]
+    fn files_published_into_new_directories() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+
+        let mut mission_code = MissionCode::parse_from(
+            "_infilengine_cm_codeless_|2|1:0|4:None|6:origin|1:2|11:mission.txt|4:main|17:missions/vent.txt|4:vent"
+        ).unwrap();
+        let outcome = publish(&repo, &mut mission_code, None, None, false, false).unwrap();
+
+        assert_eq!(commit_file(&repo, outcome.commit, "missions/vent.txt").as_deref(), Some("vent"));
+        assert_eq!(read_workdir(&repo, "missions/vent.txt").as_deref(), Some("vent"));
+    }
+
+    #[test]
+    fn in_memory_publish_leaves_workdir() {
+        let dir = setup();
+        let remote_path = init_remote(dir.path(), &[(MISSION_FILE, "old")]);
+        let repo = clone(&remote_path, &dir.path().join("work"), false);
+
+        fs::write(repo.workdir().unwrap().join(MISSION_FILE), "editing").unwrap();
+        let outcome = publish_data(&repo, "new", true).unwrap();
+
+        assert_eq!(commit_file(&repo, outcome.commit, MISSION_FILE).as_deref(), Some("new"));
+        assert_eq!(read_workdir(&repo, MISSION_FILE).as_deref(), Some("editing"));
+        assert_eq!(read_workdir(&repo, VERSION_TABLE_FILE), None);
+    }
+}
@@ -7,7 +7,7 @@ use rouille::{Response, ResponseBody};
 
 use crate::program_info::{self, ProgramInfo};
 
-use crate::{cmterm, repo_management};
+use crate::{cmterm, features, repo_management};
 use crate::mission_codes;
 
 #[derive(Debug)]
@@ -67,7 +67,7 @@ fn server_requests_loop(request: &Request, repo: &Repository, log: &cmterm::Log)
 
     // Allocate 200,000 bytes
     // This is the maximum size of a single code, so I figure it's a reasonable default
-    let mut body_read = Vec::<u8>::with_capacity(200_000);
+    let mut body_read = Vec::<u8>::with_capacity(mission_codes::MAX_CODE_SIZE);
     match reqbody.read_to_end(&mut body_read) {
         Ok(_) => (),
         Err(e) => {
@@ -96,6 +96,11 @@ fn server_requests_loop(request: &Request, repo: &Repository, log: &cmterm::Log)
 
     let program_args = program_info::get_args();
 
+    match mission_code.report_unknown_features(program_args.strict_features, log) {
+        Ok(_) => (),
+        Err(e) => return server_error(format!("error \'{e}\' encountered while validating mission code features"))
+    };
+
     let gist_url = match &mission_code.gist_url {
         Some(s) => s.clone(),
         None => repo_management::remote_url_from_name(repo, &mission_code.gist_remote.as_ref().expect("Mission should have remote to be valid")).expect("URL should exist for remote").expect("URL should exist for remote")
@@ -108,9 +113,9 @@ fn server_requests_loop(request: &Request, repo: &Repository, log: &cmterm::Log)
 
     log.log_success(
         format!(
-            "Parsed sent mission code, details are as follows:\nVersion: {}\nGist File: {}\nGist URL: {}\nGist Remote: {}\nFeature Count: {}\nFeatures: [{}]",
+            "Parsed sent mission code, details are as follows:\nVersion: {}\nGist Files: {}\nGist URL: {}\nGist Remote: {}\nFeature Count: {}\nFeatures: [{}]",
             mission_code.codeless_fmt_version.version(),
-            &mission_code.gist_file,
+            mission_code.files().iter().map(|(name, _)| { *name }).collect::<Vec<&str>>().join(", "),
             gist_url_display,
             &mission_code.gist_remote.as_ref().unwrap_or(&String::from("None")),
             mission_code.codeless_features.len(),
@@ -119,8 +124,22 @@ fn server_requests_loop(request: &Request, repo: &Repository, log: &cmterm::Log)
     );
 
     log.log("Attempting to commit to repo...");
-    match repo_management::publish(repo, &mut mission_code, None, None) {
-        Ok(_) => log.log_success("Success...?"),
+    let (author, author_email) = mission_code.commit_author();
+    match repo_management::publish(repo, &mut mission_code, author, author_email, program_args.in_memory, true) {
+        Ok(outcome) => {
+            match outcome.unchanged {
+                true => log.log_success(format!("Mission data unchanged since commit {}, reused existing link", outcome.commit)),
+                false => log.log_success(format!("Published mission version {}", mission_code.version_display().unwrap_or(String::from("Untracked"))))
+            };
+
+            for file in outcome.files {
+                let url_display = match program_args.hide_url {
+                    true => "*".repeat(file.url.len()),
+                    false => file.url
+                };
+                log.log(format!("Raw URL ({}): {}", file.file, url_display));
+            }
+        },
         Err(e) => {
             log.log_err(e.to_string());
         }
@@ -133,7 +152,18 @@ pub fn start(program: &ProgramInfo) -> Result<(JoinHandle<()>, Sender<()>), Serv
     let program_args = program_info::get_args();
 
     let srvr_log = program.srvr_log.clone();
-    let repo = Mutex::new(repo_management::get_repo(program.repo_path.as_ref().expect(""))?);
+    let repo = repo_management::get_repo(program.repo_path.as_ref().expect(""))?;
+    match repo.config() {
+        Ok(config) => features::external::register_from_config(&config, &program.srvr_log),
+        Err(e) => program.srvr_log.log_warn(format!("Failed to open git config, external features will be unavailable\n{}", e))
+    };
+
+    match repo_management::publish_identity(&repo, &program.srvr_log) {
+        Ok(identity) => program.srvr_log.log(format!("Publishing as {}", identity)),
+        Err(e) => program.srvr_log.log_warn(format!("Failed to resolve publish identity from git config\n{}", e))
+    };
+
+    let repo = Mutex::new(repo);
     let server_start_result = rouille::Server::new(format!("localhost:{}", program_args.port), move | request | {
         cmterm::Log::set(srvr_log.clone());
         let repo = &repo;